@@ -0,0 +1,1121 @@
+// src-tauri/src/audio.rs
+//
+// Sample-processing helpers shared by every capture callback. Nothing in here
+// touches cpal streams or Tauri, so it can be exercised on plain buffers.
+use cpal::{FromSample, Sample};
+use rubato::{FftFixedIn, Resampler};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Which input channel(s) end up in the mono recording.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChannelSelect {
+  /// Average every channel of the frame.
+  Downmix,
+  /// Keep a single channel (0-based).
+  Channel(usize),
+}
+
+impl ChannelSelect {
+  /// Resolve the `input_channel` setting against the stream's channel count.
+  /// An out-of-range channel falls back to downmixing rather than recording silence.
+  pub fn from_setting(channel: Option<u16>, channels: usize) -> Self {
+    match channel {
+      Some(c) if (c as usize) < channels => ChannelSelect::Channel(c as usize),
+      Some(c) => {
+        eprintln!(
+          "Warning: input channel {} not available ({} channels), downmixing instead",
+          c + 1,
+          channels
+        );
+        ChannelSelect::Downmix
+      }
+      None => ChannelSelect::Downmix,
+    }
+  }
+}
+
+/// Mix one interleaved frame (already normalised to [-1.0, 1.0]) down to a single sample.
+#[inline]
+pub fn mix_frame(frame: &[f32], select: ChannelSelect) -> f32 {
+  match select {
+    ChannelSelect::Channel(c) => frame.get(c).copied().unwrap_or(0.0),
+    ChannelSelect::Downmix => {
+      if frame.is_empty() {
+        return 0.0;
+      }
+      // Averaging keeps the mix within full scale no matter how many channels
+      // carry signal, so there is no need for a separate limiter.
+      frame.iter().sum::<f32>() / frame.len() as f32
+    }
+  }
+}
+
+/// Normalise any sample format cpal can deliver to [-1.0, 1.0].
+/// Unsigned formats are centred on their midpoint (u16 32768 -> 0.0), which
+/// `FromSample` handles for every width.
+#[inline]
+pub fn sample_to_f32<T>(s: T) -> f32
+where
+  T: Sample,
+  f32: FromSample<T>,
+{
+  f32::from_sample(s)
+}
+
+/// Convert a normalised float sample to 16-bit PCM.
+#[inline]
+pub fn f32_to_i16(v: f32) -> i16 {
+  (v.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+/// Downmix interleaved input of any sample format to mono, one normalised
+/// sample per frame. Allocation-free, so it can run in the audio callback.
+pub fn downmix_iter<T>(data: &[T], channels: usize, select: ChannelSelect) -> impl Iterator<Item = f32> + '_
+where
+  T: Sample,
+  f32: FromSample<T>,
+{
+  data.chunks(channels).map(move |raw| {
+    // Channel counts above 32 are not something cpal reports in practice;
+    // anything past that is ignored rather than allocating per frame.
+    let mut frame = [0f32; 32];
+    let n = raw.len().min(frame.len());
+    for (dst, &src) in frame[..n].iter_mut().zip(raw) {
+      *dst = sample_to_f32(src);
+    }
+    mix_frame(&frame[..n], select)
+  })
+}
+
+/// Fixed-capacity ring holding the most recent mono samples (warm-mic pre-roll).
+pub struct PrerollBuffer {
+  buf: VecDeque<f32>,
+  capacity: usize,
+}
+
+impl PrerollBuffer {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      buf: VecDeque::with_capacity(capacity),
+      capacity,
+    }
+  }
+
+  pub fn push(&mut self, samples: &[f32]) {
+    if self.capacity == 0 {
+      return;
+    }
+    let samples = &samples[samples.len().saturating_sub(self.capacity)..];
+    let overflow = (self.buf.len() + samples.len()).saturating_sub(self.capacity);
+    self.buf.drain(..overflow);
+    self.buf.extend(samples);
+  }
+
+  /// Drain the buffered audio, oldest first.
+  pub fn take(&mut self) -> Vec<f32> {
+    self.buf.drain(..).collect()
+  }
+
+  pub fn clear(&mut self) {
+    self.buf.clear();
+  }
+}
+
+/// Pick the rate the WAV is written at. Upsampling only makes uploads bigger,
+/// so a target at or above the device rate (or 0) keeps the device rate.
+pub fn output_rate(device_rate: u32, target_rate: u32) -> u32 {
+  if target_rate == 0 || target_rate >= device_rate {
+    device_rate
+  } else {
+    target_rate
+  }
+}
+
+const RESAMPLER_CHUNK: usize = 1024;
+
+/// Input chunk for the FFT resampler, close to `RESAMPLER_CHUNK`. Its filter is
+/// centred half an FFT in, which is a whole number of output frames only when
+/// the FFT spans an even number of the smallest in/out rate ratio; otherwise
+/// trimming the reported delay leaves the output a fraction of a frame late.
+fn resampler_chunk(in_rate: u32, out_rate: u32) -> usize {
+  let (mut a, mut b) = (in_rate, out_rate);
+  while b != 0 {
+    (a, b) = (b, a % b);
+  }
+  let unit = 2 * (in_rate / a) as usize;
+  RESAMPLER_CHUNK.div_ceil(unit) * unit
+}
+
+/// Streaming mono resampler between the capture callback and the WAV writer.
+/// Input arrives in whatever chunk sizes the device delivers; it is buffered
+/// into fixed chunks for the FFT resampler and the filter delay is trimmed so
+/// the output lines up with the input sample-for-sample.
+pub struct MonoResampler {
+  inner: Option<FftFixedIn<f32>>,
+  pending: Vec<f32>,
+  out_buf: Vec<Vec<f32>>,
+  in_rate: u32,
+  out_rate: u32,
+  // leading output frames that are only filter delay
+  skip: usize,
+  frames_in: u64,
+  frames_out: u64,
+}
+
+impl MonoResampler {
+  pub fn new(in_rate: u32, out_rate: u32) -> Result<Self, String> {
+    let inner = if in_rate == out_rate {
+      None
+    } else {
+      Some(
+        FftFixedIn::<f32>::new(
+          in_rate as usize,
+          out_rate as usize,
+          resampler_chunk(in_rate, out_rate),
+          1,
+          1,
+        )
+          .map_err(|e| format!("resampler: {e}"))?,
+      )
+    };
+    let out_buf = inner
+      .as_ref()
+      .map(|r| r.output_buffer_allocate(true))
+      .unwrap_or_default();
+    let skip = inner.as_ref().map(|r| r.output_delay()).unwrap_or(0);
+    Ok(Self {
+      inner,
+      pending: Vec::with_capacity(RESAMPLER_CHUNK * 2),
+      out_buf,
+      in_rate,
+      out_rate,
+      skip,
+      frames_in: 0,
+      frames_out: 0,
+    })
+  }
+
+  pub fn output_rate(&self) -> u32 {
+    self.out_rate
+  }
+
+  pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) -> Result<(), String> {
+    self.frames_in += input.len() as u64;
+    let Some(inner) = self.inner.as_mut() else {
+      out.extend_from_slice(input);
+      self.frames_out += input.len() as u64;
+      return Ok(());
+    };
+
+    self.pending.extend_from_slice(input);
+    let mut consumed = 0;
+    while self.pending.len() - consumed >= inner.input_frames_next() {
+      let need = inner.input_frames_next();
+      let (_, produced) = inner
+        .process_into_buffer(&[&self.pending[consumed..consumed + need]], &mut self.out_buf, None)
+        .map_err(|e| format!("resample: {e}"))?;
+      consumed += need;
+
+      let skip = self.skip.min(produced);
+      self.skip -= skip;
+      out.extend_from_slice(&self.out_buf[0][skip..produced]);
+      self.frames_out += (produced - skip) as u64;
+    }
+    self.pending.drain(..consumed);
+    Ok(())
+  }
+
+  /// Push out whatever is still buffered, trimmed to exactly
+  /// `frames_in * out_rate / in_rate` output frames.
+  pub fn flush(&mut self, out: &mut Vec<f32>) -> Result<(), String> {
+    let Some(inner) = self.inner.as_mut() else {
+      return Ok(());
+    };
+
+    let expected = self.frames_in * self.out_rate as u64 / self.in_rate as u64;
+    while self.frames_out < expected {
+      let pending = std::mem::take(&mut self.pending);
+      // rubato treats an empty channel slice as "inactive", so pass None once drained
+      let input = if pending.is_empty() { None } else { Some(&[&pending[..]][..]) };
+      let (_, produced) = inner
+        .process_partial_into_buffer(input, &mut self.out_buf, None)
+        .map_err(|e| format!("resample: {e}"))?;
+
+      let skip = self.skip.min(produced);
+      self.skip -= skip;
+      let wanted = (expected - self.frames_out) as usize;
+      let take = (produced - skip).min(wanted);
+      out.extend_from_slice(&self.out_buf[0][skip..skip + take]);
+      self.frames_out += take as u64;
+    }
+    Ok(())
+  }
+}
+
+/// How system audio (a monitor/loopback capture) is recorded next to the mic.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SystemAudioMode {
+  Off,
+  /// Mic and system audio averaged into one mono channel.
+  Mix,
+  /// Stereo file: mic on the left channel, system audio on the right.
+  Separate,
+}
+
+impl SystemAudioMode {
+  /// Resolve the `system_audio` setting; unknown values record the mic only.
+  pub fn from_setting(mode: &str) -> Self {
+    match mode {
+      "mix" => SystemAudioMode::Mix,
+      "separate" => SystemAudioMode::Separate,
+      _ => SystemAudioMode::Off,
+    }
+  }
+
+  pub fn channels(self) -> u16 {
+    match self {
+      SystemAudioMode::Separate => 2,
+      _ => 1,
+    }
+  }
+}
+
+/// Lines up two mono streams at the same rate (mic and system audio) that
+/// arrive in independently sized chunks from two devices.
+pub struct SourceMixer {
+  mode: SystemAudioMode,
+  mic: VecDeque<f32>,
+  system: VecDeque<f32>,
+  // how far one side may run ahead before the other is assumed to have stalled
+  max_lag: usize,
+}
+
+impl SourceMixer {
+  pub fn new(mode: SystemAudioMode, max_lag: usize) -> Self {
+    Self {
+      mode,
+      mic: VecDeque::new(),
+      system: VecDeque::new(),
+      max_lag,
+    }
+  }
+
+  pub fn push_mic(&mut self, samples: &[f32]) {
+    self.mic.extend(samples);
+  }
+
+  pub fn push_system(&mut self, samples: &[f32]) {
+    self.system.extend(samples);
+  }
+
+  /// Move every frame both sides have into `file` (interleaved in Separate
+  /// mode) and its mono mix into `mono`. A side more than `max_lag` behind is
+  /// padded with silence, e.g. a monitor source that stops delivering while
+  /// nothing plays; `flush` pads the shorter side to the end.
+  pub fn drain(&mut self, flush: bool, file: &mut Vec<f32>, mono: &mut Vec<f32>) {
+    let longer = self.mic.len().max(self.system.len());
+    let pad_to = if flush { longer } else { longer.saturating_sub(self.max_lag) };
+    for side in [&mut self.mic, &mut self.system] {
+      if side.len() < pad_to {
+        side.resize(pad_to, 0.0);
+      }
+    }
+
+    let frames = self.mic.len().min(self.system.len());
+    for (m, s) in self.mic.drain(..frames).zip(self.system.drain(..frames)) {
+      let mixed = (m + s) * 0.5;
+      match self.mode {
+        SystemAudioMode::Separate => file.extend_from_slice(&[m, s]),
+        _ => file.push(mixed),
+      }
+      mono.push(mixed);
+    }
+  }
+}
+
+/// Meter readings per second emitted while recording.
+pub const LEVEL_UPDATES_PER_SEC: u32 = 25;
+
+/// One meter reading over the last window, linear and in dBFS.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct Level {
+  pub rms: f32,
+  pub peak: f32,
+  pub rms_db: f32,
+  pub peak_db: f32,
+}
+
+fn to_dbfs(v: f32) -> f32 {
+  // floor at -100 dB so digital silence serialises as a number, not -inf
+  (20.0 * v.max(1e-5).log10()).max(-100.0)
+}
+
+/// Accumulates RMS and peak over fixed windows of mono samples.
+pub struct LevelMeter {
+  window: usize,
+  count: usize,
+  sum_sq: f64,
+  peak: f32,
+}
+
+impl LevelMeter {
+  pub fn new(sample_rate: u32) -> Self {
+    Self {
+      window: (sample_rate / LEVEL_UPDATES_PER_SEC).max(1) as usize,
+      count: 0,
+      sum_sq: 0.0,
+      peak: 0.0,
+    }
+  }
+
+  /// Feed samples; returns the most recent completed window, if any.
+  pub fn push(&mut self, samples: &[f32]) -> Option<Level> {
+    let mut reading = None;
+    for &s in samples {
+      self.sum_sq += (s as f64) * (s as f64);
+      self.peak = self.peak.max(s.abs());
+      self.count += 1;
+      if self.count == self.window {
+        let rms = (self.sum_sq / self.count as f64).sqrt() as f32;
+        reading = Some(Level {
+          rms,
+          peak: self.peak,
+          rms_db: to_dbfs(rms),
+          peak_db: to_dbfs(self.peak),
+        });
+        self.count = 0;
+        self.sum_sq = 0.0;
+        self.peak = 0.0;
+      }
+    }
+    reading
+  }
+}
+
+/// Samples at or above this magnitude are counted as clipped (full scale
+/// after conversion to i16).
+const CLIP_LEVEL: f32 = 32767.0 / 32768.0;
+/// Stats frames quieter than this count towards the silence ratio.
+const SILENCE_DB: f32 = -50.0;
+const STATS_FRAME_MS: u32 = 20;
+
+/// Summary of the samples written to a recording.
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+pub struct RecordingStats {
+  pub duration_ms: u64,
+  pub samples: u64,
+  pub peak: f32,
+  pub rms: f32,
+  pub peak_db: f32,
+  pub rms_db: f32,
+  pub clipped_samples: u64,
+  /// Fraction (0..1) of 20 ms frames below -50 dBFS.
+  pub silence_ratio: f32,
+  /// RMS of the loudest 20 ms frame; unlike peak, a single click doesn't raise it.
+  pub loudest_frame_db: f32,
+}
+
+/// Accumulates [`RecordingStats`] over everything the writer writes.
+pub struct StatsAccumulator {
+  sample_rate: u32,
+  samples: u64,
+  sum_sq: f64,
+  peak: f32,
+  clipped: u64,
+  frame_len: usize,
+  frame_count: usize,
+  frame_sum_sq: f64,
+  frames: u64,
+  silent_frames: u64,
+  loudest_frame: f32,
+}
+
+impl StatsAccumulator {
+  pub fn new(sample_rate: u32) -> Self {
+    Self {
+      sample_rate,
+      samples: 0,
+      sum_sq: 0.0,
+      peak: 0.0,
+      clipped: 0,
+      frame_len: (sample_rate * STATS_FRAME_MS / 1000).max(1) as usize,
+      frame_count: 0,
+      frame_sum_sq: 0.0,
+      frames: 0,
+      silent_frames: 0,
+      loudest_frame: 0.0,
+    }
+  }
+
+  pub fn push(&mut self, samples: &[f32]) {
+    for &s in samples {
+      let sq = (s as f64) * (s as f64);
+      let mag = s.abs();
+      self.samples += 1;
+      self.sum_sq += sq;
+      self.peak = self.peak.max(mag);
+      if mag >= CLIP_LEVEL {
+        self.clipped += 1;
+      }
+      self.frame_sum_sq += sq;
+      self.frame_count += 1;
+      if self.frame_count == self.frame_len {
+        self.end_frame();
+      }
+    }
+  }
+
+  fn end_frame(&mut self) {
+    let rms = (self.frame_sum_sq / self.frame_count as f64).sqrt() as f32;
+    self.frames += 1;
+    self.loudest_frame = self.loudest_frame.max(rms);
+    if to_dbfs(rms) < SILENCE_DB {
+      self.silent_frames += 1;
+    }
+    self.frame_count = 0;
+    self.frame_sum_sq = 0.0;
+  }
+
+  pub fn finish(mut self) -> RecordingStats {
+    // a trailing partial frame still counts
+    if self.frame_count > 0 {
+      self.end_frame();
+    }
+    let rms = if self.samples > 0 {
+      (self.sum_sq / self.samples as f64).sqrt() as f32
+    } else {
+      0.0
+    };
+    RecordingStats {
+      duration_ms: self.samples * 1000 / self.sample_rate.max(1) as u64,
+      samples: self.samples,
+      peak: self.peak,
+      rms,
+      peak_db: to_dbfs(self.peak),
+      rms_db: to_dbfs(rms),
+      clipped_samples: self.clipped,
+      silence_ratio: if self.frames > 0 {
+        self.silent_frames as f32 / self.frames as f32
+      } else {
+        1.0
+      },
+      loudest_frame_db: to_dbfs(self.loudest_frame),
+    }
+  }
+}
+
+// frames read per step when streaming through a WAV file
+const READ_BLOCK: usize = 4096;
+
+/// Compute [`RecordingStats`] for a WAV file of any PCM layout, mixing
+/// channels down to mono. Reads a block at a time, so an hour-long recording
+/// costs no more memory than a short one.
+pub fn analyze_wav(path: &std::path::Path) -> Result<RecordingStats, String> {
+  let mut reader = hound::WavReader::open(path).map_err(|e| format!("wav open: {e}"))?;
+  let spec = reader.spec();
+  let channels = spec.channels.max(1) as usize;
+  let mut samples: Box<dyn Iterator<Item = Result<f32, hound::Error>>> = match spec.sample_format {
+    hound::SampleFormat::Float => Box::new(reader.samples::<f32>()),
+    hound::SampleFormat::Int => {
+      let scale = (1u64 << (spec.bits_per_sample.clamp(1, 32) - 1)) as f32;
+      Box::new(reader.samples::<i32>().map(move |s| s.map(|v| v as f32 / scale)))
+    }
+  };
+
+  let mut stats = StatsAccumulator::new(spec.sample_rate);
+  let (mut block, mut mono) = (Vec::new(), Vec::new());
+  loop {
+    block.clear();
+    for s in samples.by_ref().take(READ_BLOCK * channels) {
+      block.push(s.map_err(|e| format!("wav read: {e}"))?);
+    }
+    if block.is_empty() {
+      break;
+    }
+    mono.clear();
+    mono.extend(
+      block
+        .chunks_exact(channels)
+        .map(|frame| mix_frame(frame, ChannelSelect::Downmix)),
+    );
+    stats.push(&mono);
+  }
+  Ok(stats.finish())
+}
+
+/// One piece of a recording cut by [`split_wav`].
+pub struct WavChunk {
+  pub path: std::path::PathBuf,
+  pub stats: RecordingStats,
+}
+
+/// Cut a 16-bit WAV into files of at most `max_ms`, each ending at the first
+/// pause after half that (or at `max_ms` when nobody pauses). Chunk `i` is
+/// written to `chunk_path(i)`.
+pub fn split_wav(
+  path: &std::path::Path,
+  max_ms: u32,
+  vad_config: VadConfig,
+  chunk_path: impl Fn(usize) -> std::path::PathBuf,
+) -> Result<Vec<WavChunk>, String> {
+  type ChunkWriter = hound::WavWriter<std::io::BufWriter<std::fs::File>>;
+
+  let mut reader = hound::WavReader::open(path).map_err(|e| format!("wav open: {e}"))?;
+  let spec = reader.spec();
+  if spec.bits_per_sample != 16 || spec.sample_format != hound::SampleFormat::Int {
+    return Err(format!("cannot split {}-bit audio", spec.bits_per_sample));
+  }
+  let channels = spec.channels.max(1) as usize;
+  let mut splitter = SegmentSplitter::new(spec.sample_rate, vad_config, max_ms / 2, max_ms);
+
+  let mut chunks = Vec::new();
+  let mut current: Option<(ChunkWriter, StatsAccumulator)> = None;
+  let close = |current: &mut Option<(ChunkWriter, StatsAccumulator)>, chunks: &mut Vec<WavChunk>| {
+    if let Some((writer, stats)) = current.take() {
+      writer.finalize().map_err(|e| format!("wav finalize: {e}"))?;
+      chunks.push(WavChunk {
+        path: chunk_path(chunks.len()),
+        stats: stats.finish(),
+      });
+    }
+    Ok::<_, String>(())
+  };
+
+  let mut samples = reader.samples::<i16>();
+  let (mut block, mut frames, mut mono, mut cuts) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+  loop {
+    block.clear();
+    for s in samples.by_ref().take(READ_BLOCK * channels) {
+      block.push(s.map_err(|e| format!("wav read: {e}"))?);
+    }
+    if block.is_empty() {
+      break;
+    }
+    frames.clear();
+    frames.extend(block.iter().map(|&s| sample_to_f32(s)));
+    mono.clear();
+    mono.extend(
+      frames
+        .chunks_exact(channels)
+        .map(|frame| mix_frame(frame, ChannelSelect::Downmix)),
+    );
+    cuts.clear();
+    splitter.push(&mono, &mut cuts);
+
+    let mut start = 0;
+    // (end, whether the chunk ends there) for every cut, then the rest of the block
+    for (end, cut) in cuts.iter().map(|&c| (c, true)).chain([(mono.len(), false)]) {
+      if end > start {
+        let (writer, stats) = match current.as_mut() {
+          Some(open) => open,
+          None => current.insert((
+            hound::WavWriter::create(chunk_path(chunks.len()), spec).map_err(|e| format!("wav create: {e}"))?,
+            StatsAccumulator::new(spec.sample_rate),
+          )),
+        };
+        for &s in &block[start * channels..end * channels] {
+          writer.write_sample(s).map_err(|e| format!("wav write: {e}"))?;
+        }
+        stats.push(&mono[start..end]);
+      }
+      if cut {
+        close(&mut current, &mut chunks)?;
+      }
+      start = end;
+    }
+  }
+  close(&mut current, &mut chunks)?;
+  Ok(chunks)
+}
+
+/// Tuning for the energy-based voice activity detector.
+#[derive(Clone, Copy, Debug)]
+pub struct VadConfig {
+  /// Frames quieter than this never count as speech.
+  pub threshold_db: f32,
+  /// Silence after speech that ends the utterance.
+  pub silence_ms: u32,
+}
+
+const VAD_FRAME_MS: u32 = 20;
+// consecutive loud frames before we believe it is speech and not a click
+const VAD_ONSET_FRAMES: u32 = 3;
+// speech has to stand this far above the tracked noise floor
+const VAD_NOISE_MARGIN_DB: f32 = 10.0;
+
+/// Energy-based voice activity detector with onset and hangover.
+///
+/// Audio is cut into 20 ms frames. A frame is "loud" when its RMS is above
+/// both the fixed threshold and the adaptive noise floor plus a margin.
+/// Speech starts after a few consecutive loud frames; once speech has been
+/// seen, `silence_ms` of quiet frames (the hangover) ends the utterance.
+pub struct VoiceActivityDetector {
+  frame_len: usize,
+  threshold: f32,
+  silence_frames: u32,
+  acc_sq: f64,
+  acc_len: usize,
+  noise_floor: Option<f32>,
+  loud_run: u32,
+  quiet_run: u32,
+  speech_seen: bool,
+  ended: bool,
+}
+
+impl VoiceActivityDetector {
+  pub fn new(sample_rate: u32, config: VadConfig) -> Self {
+    Self {
+      frame_len: (sample_rate * VAD_FRAME_MS / 1000).max(1) as usize,
+      threshold: 10f32.powf(config.threshold_db / 20.0),
+      silence_frames: config.silence_ms.div_ceil(VAD_FRAME_MS).max(1),
+      acc_sq: 0.0,
+      acc_len: 0,
+      noise_floor: None,
+      loud_run: 0,
+      quiet_run: 0,
+      speech_seen: false,
+      ended: false,
+    }
+  }
+
+  /// Feed samples; returns true once speech has been followed by enough silence.
+  /// Stays true afterwards.
+  pub fn push(&mut self, samples: &[f32]) -> bool {
+    for &s in samples {
+      if self.ended {
+        break;
+      }
+      self.acc_sq += (s as f64) * (s as f64);
+      self.acc_len += 1;
+      if self.acc_len == self.frame_len {
+        let rms = (self.acc_sq / self.acc_len as f64).sqrt() as f32;
+        self.acc_sq = 0.0;
+        self.acc_len = 0;
+        self.on_frame(rms);
+      }
+    }
+    self.ended
+  }
+
+  fn on_frame(&mut self, rms: f32) {
+    let margin = 10f32.powf(VAD_NOISE_MARGIN_DB / 20.0);
+    let floor = *self.noise_floor.get_or_insert(rms);
+    let loud = rms > self.threshold && rms > floor * margin;
+
+    // minimum tracking: drop straight to quieter frames, creep up slowly so
+    // steady background noise is learned but speech doesn't become "noise"
+    self.noise_floor = Some(if rms < floor { rms } else { floor + (rms - floor) * 0.002 });
+
+    if loud {
+      self.loud_run += 1;
+      self.quiet_run = 0;
+      if self.loud_run >= VAD_ONSET_FRAMES {
+        self.speech_seen = true;
+      }
+    } else {
+      self.loud_run = 0;
+      self.quiet_run += 1;
+      if self.speech_seen && self.quiet_run >= self.silence_frames {
+        self.ended = true;
+      }
+    }
+  }
+}
+
+/// Cuts a running recording into segments for incremental transcription: at
+/// the first pause after `min_ms` (the detector's hangover, so a cut lands in
+/// silence rather than mid-word), or unconditionally at `max_ms`.
+pub struct SegmentSplitter {
+  sample_rate: u32,
+  vad_config: VadConfig,
+  vad: VoiceActivityDetector,
+  frame_len: usize,
+  min_len: usize,
+  max_len: usize,
+  // samples in the current segment
+  len: usize,
+}
+
+impl SegmentSplitter {
+  pub fn new(sample_rate: u32, vad_config: VadConfig, min_ms: u32, max_ms: u32) -> Self {
+    let samples = |ms: u32| (sample_rate as u64 * ms as u64 / 1000).max(1) as usize;
+    Self {
+      sample_rate,
+      vad_config,
+      vad: VoiceActivityDetector::new(sample_rate, vad_config),
+      frame_len: samples(VAD_FRAME_MS),
+      min_len: samples(min_ms),
+      max_len: samples(max_ms.max(min_ms)),
+      len: 0,
+    }
+  }
+
+  /// Feed mono samples; appends to `cuts` every offset into `samples` at which
+  /// the current segment ends and the next begins.
+  pub fn push(&mut self, samples: &[f32], cuts: &mut Vec<usize>) {
+    let mut start = 0;
+    while start < samples.len() {
+      // frame-sized steps so a cut is never more than a frame late
+      let end = samples.len().min(start + self.frame_len - self.len % self.frame_len);
+      let paused = self.vad.push(&samples[start..end]);
+      self.len += end - start;
+      if (paused && self.len >= self.min_len) || self.len >= self.max_len {
+        cuts.push(end);
+        self.len = 0;
+        self.vad = VoiceActivityDetector::new(self.sample_rate, self.vad_config);
+      } else if paused {
+        // a pause too early in the segment; wait for the next one
+        self.vad = VoiceActivityDetector::new(self.sample_rate, self.vad_config);
+      }
+      start = end;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::{self, tone, TempDir};
+
+  fn mono_i16<T>(data: &[T], channels: usize) -> Vec<i16>
+  where
+    T: Sample,
+    f32: FromSample<T>,
+  {
+    let mut out = Vec::new();
+    out.extend(downmix_iter(data, channels, ChannelSelect::Downmix));
+    out.into_iter().map(f32_to_i16).collect()
+  }
+
+  #[test]
+  fn converts_i8() {
+    assert_eq!(mono_i16(&[0i8, i8::MIN, i8::MAX, 64], 1), vec![0, -32767, 32511, 16384]);
+  }
+
+  #[test]
+  fn converts_i16() {
+    assert_eq!(mono_i16(&[0i16, i16::MIN, i16::MAX, -1234], 1), vec![0, -32767, 32766, -1234]);
+  }
+
+  #[test]
+  fn converts_i32() {
+    // 24-in-32 devices put the sample in the top bytes; the low byte is noise
+    let s24 = 0x1234_5600i32;
+    assert_eq!(mono_i16(&[0i32, i32::MIN, i32::MAX, s24 | 0x7f], 1), vec![0, -32767, 32767, 0x1234]);
+  }
+
+  #[test]
+  fn converts_i64() {
+    assert_eq!(mono_i16(&[0i64, i64::MIN, i64::MAX], 1), vec![0, -32767, 32767]);
+  }
+
+  #[test]
+  fn converts_u8() {
+    assert_eq!(mono_i16(&[128u8, 0, 255, 192], 1), vec![0, -32767, 32511, 16384]);
+  }
+
+  #[test]
+  fn converts_u16_with_midpoint_offset() {
+    // the hand-rolled `v as i32 - 32768` this replaces
+    for v in [0u16, 1, 16384, 32767, 32768, 32769, 49152, 65535] {
+      let expected = (v as i32 - 32768).clamp(-32767, 32767) as i16;
+      let got = mono_i16(&[v], 1)[0];
+      assert!((got - expected).abs() <= 1, "u16 {v}: got {got}, expected {expected}");
+    }
+  }
+
+  #[test]
+  fn converts_u32() {
+    assert_eq!(mono_i16(&[1u32 << 31, 0, u32::MAX], 1), vec![0, -32767, 32767]);
+  }
+
+  #[test]
+  fn converts_u64() {
+    assert_eq!(mono_i16(&[1u64 << 63, 0, u64::MAX], 1), vec![0, -32767, 32767]);
+  }
+
+  #[test]
+  fn converts_f32() {
+    assert_eq!(mono_i16(&[0.0f32, -1.0, 1.0, 0.5, 1.5, -2.0], 1), vec![0, -32767, 32767, 16384, 32767, -32767]);
+  }
+
+  #[test]
+  fn converts_f64() {
+    assert_eq!(mono_i16(&[0.0f64, -1.0, 1.0, -0.25], 1), vec![0, -32767, 32767, -8192]);
+  }
+
+  #[test]
+  fn downmix_averages_channels() {
+    let stereo = [0.5f32, 0.5, 1.0, -1.0, 0.0, 0.8];
+    let mut out = Vec::new();
+    out.extend(downmix_iter(&stereo, 2, ChannelSelect::Downmix));
+    assert_eq!(out, vec![0.5, 0.0, 0.4]);
+  }
+
+  #[test]
+  fn selects_single_channel() {
+    let stereo = [0i16, 1000, 0, -1000];
+    let mut out = Vec::new();
+    out.extend(downmix_iter(&stereo, 2, ChannelSelect::from_setting(Some(1), 2)));
+    assert_eq!(out.into_iter().map(f32_to_i16).collect::<Vec<_>>(), vec![1000, -1000]);
+  }
+
+  fn sine(rate: u32, frames: usize) -> Vec<f32> {
+    (0..frames)
+      .map(|n| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * n as f32 / rate as f32).sin())
+      .collect()
+  }
+
+  /// Feed `input` through a resampler `chunk` frames at a time, as the
+  /// capture callback would, then flush.
+  fn resample(input: &[f32], in_rate: u32, out_rate: u32, chunk: usize) -> Vec<f32> {
+    let mut resampler = MonoResampler::new(in_rate, out_rate).unwrap();
+    let mut out = Vec::new();
+    for c in input.chunks(chunk) {
+      resampler.process(c, &mut out).unwrap();
+    }
+    resampler.flush(&mut out).unwrap();
+    out
+  }
+
+  #[test]
+  fn resampler_output_length_follows_the_ratio() {
+    for (in_rate, out_rate) in [(48000, 16000), (44100, 16000), (48000, 44100)] {
+      // not a whole number of resampler chunks
+      let frames = in_rate as usize + 123;
+      let input = sine(in_rate, frames);
+      for chunk in [7, 441, 4096] {
+        let out = resample(&input, in_rate, out_rate, chunk);
+        assert_eq!(
+          out.len() as u64,
+          frames as u64 * out_rate as u64 / in_rate as u64,
+          "{in_rate} -> {out_rate} in chunks of {chunk}"
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn resampler_lines_up_with_the_input() {
+    for (in_rate, chunk) in [(48000, 441), (44100, 7)] {
+      let out = resample(&sine(in_rate, in_rate as usize), in_rate, 16000, chunk);
+      let ideal = sine(16000, out.len());
+      // the filter rings at the very edges; in between it must match sample for
+      // sample (even a third of a frame late is an error of about 0.03)
+      let error = out[100..out.len() - 100]
+        .iter()
+        .zip(&ideal[100..])
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0f32, f32::max);
+      assert!(error < 0.001, "{in_rate} in chunks of {chunk}: error {error}");
+    }
+  }
+
+  #[test]
+  fn resampler_passes_equal_rates_through() {
+    let input = sine(16000, 1000);
+    assert_eq!(resample(&input, 16000, 16000, 7), input);
+  }
+
+  #[test]
+  fn mixer_averages_aligned_frames() {
+    let mut mixer = SourceMixer::new(SystemAudioMode::Mix, 100);
+    mixer.push_mic(&[0.5, 0.5, 0.5]);
+    mixer.push_system(&[0.25, -0.5]);
+    let (mut file, mut mono) = (Vec::new(), Vec::new());
+    mixer.drain(false, &mut file, &mut mono);
+    assert_eq!(file, vec![0.375, 0.0]);
+    assert_eq!(mono, file);
+    // the third mic sample waits for system audio until the end
+    mixer.drain(true, &mut file, &mut mono);
+    assert_eq!(file, vec![0.375, 0.0, 0.25]);
+  }
+
+  #[test]
+  fn mixer_interleaves_separate_channels() {
+    let mut mixer = SourceMixer::new(SystemAudioMode::Separate, 100);
+    mixer.push_mic(&[0.5, -0.5]);
+    mixer.push_system(&[0.25, 0.25]);
+    let (mut file, mut mono) = (Vec::new(), Vec::new());
+    mixer.drain(false, &mut file, &mut mono);
+    assert_eq!(file, vec![0.5, 0.25, -0.5, 0.25]);
+    assert_eq!(mono, vec![0.375, -0.125]);
+  }
+
+  #[test]
+  fn mixer_pads_a_stalled_source() {
+    let mut mixer = SourceMixer::new(SystemAudioMode::Mix, 2);
+    mixer.push_mic(&[1.0; 5]);
+    let (mut file, mut mono) = (Vec::new(), Vec::new());
+    mixer.drain(false, &mut file, &mut mono);
+    // everything but the last max_lag samples goes out against silence
+    assert_eq!(file, vec![0.5; 3]);
+    mixer.push_system(&[1.0, 1.0]);
+    mixer.drain(false, &mut file, &mut mono);
+    assert_eq!(file, vec![0.5, 0.5, 0.5, 1.0, 1.0]);
+  }
+
+  #[test]
+  fn stats_duration_from_samples() {
+    let mut acc = StatsAccumulator::new(16000);
+    acc.push(&vec![0.0; 16000 + 8000]);
+    let stats = acc.finish();
+    assert_eq!(stats.samples, 24000);
+    assert_eq!(stats.duration_ms, 1500);
+    assert_eq!(stats.silence_ratio, 1.0);
+    assert_eq!(stats.clipped_samples, 0);
+  }
+
+  #[test]
+  fn stats_peak_rms_clipping_and_silence() {
+    let mut acc = StatsAccumulator::new(1000);
+    // 100 ms of full-scale square wave, then 100 ms of silence
+    let loud: Vec<f32> = (0..100).map(|i| if i % 2 == 0 { 1.2 } else { -1.0 }).collect();
+    acc.push(&loud);
+    acc.push(&[0.0; 100]);
+    let stats = acc.finish();
+    assert_eq!(stats.duration_ms, 200);
+    assert_eq!(stats.clipped_samples, 100);
+    assert!((stats.peak - 1.2).abs() < 1e-6);
+    assert!(stats.rms > 0.7 && stats.rms < 0.9, "rms {}", stats.rms);
+    assert!((stats.silence_ratio - 0.5).abs() < 1e-6);
+  }
+
+  #[test]
+  fn analyzes_wav_fixture() {
+    let (_dir, path) = fixture("stats", 16000, &[(0.0, 500), (0.5, 500)]);
+    let stats = analyze_wav(&path).unwrap();
+    assert_eq!(stats.duration_ms, 1000);
+    assert!((stats.silence_ratio - 0.5).abs() < 0.05, "silence {}", stats.silence_ratio);
+    // 0.5 amplitude sine: peak -6 dBFS, frame RMS -9 dBFS
+    assert!((stats.peak_db + 6.0).abs() < 0.1, "peak {}", stats.peak_db);
+    assert!((stats.loudest_frame_db + 9.0).abs() < 0.2, "loudest {}", stats.loudest_frame_db);
+  }
+
+  /// 16-bit mono WAV of `tone(rate, segments)`, in a directory that goes
+  /// when the returned guard is dropped.
+  fn fixture(name: &str, rate: u32, segments: &[(f32, u32)]) -> (TempDir, std::path::PathBuf) {
+    let dir = TempDir::new(name);
+    let path = test_util::write_wav(dir.join("fixture.wav"), test_util::pcm_spec(1, rate, 16), &tone(rate, segments));
+    (dir, path)
+  }
+
+  /// Run the detector over a WAV file in device-sized chunks and return the
+  /// time (ms) at which it fired.
+  fn vad_end_ms(path: &std::path::Path, config: VadConfig) -> Option<u64> {
+    let mut reader = hound::WavReader::open(path).unwrap();
+    let rate = reader.spec().sample_rate;
+    let samples: Vec<f32> = reader
+      .samples::<i16>()
+      .map(|s| sample_to_f32(s.unwrap()))
+      .collect();
+    let mut vad = VoiceActivityDetector::new(rate, config);
+    let mut fed = 0usize;
+    for chunk in samples.chunks(480) {
+      fed += chunk.len();
+      if vad.push(chunk) {
+        return Some(fed as u64 * 1000 / rate as u64);
+      }
+    }
+    None
+  }
+
+  const VAD: VadConfig = VadConfig {
+    threshold_db: -45.0,
+    silence_ms: 800,
+  };
+
+  fn split_ms(samples: &[f32], rate: u32, min_ms: u32, max_ms: u32) -> Vec<u64> {
+    let mut splitter = SegmentSplitter::new(rate, VAD, min_ms, max_ms);
+    let (mut cuts, mut fed) = (Vec::new(), 0usize);
+    for chunk in samples.chunks(333) {
+      let mut chunk_cuts = Vec::new();
+      splitter.push(chunk, &mut chunk_cuts);
+      cuts.extend(chunk_cuts.iter().map(|c| (fed + c) as u64 * 1000 / rate as u64));
+      fed += chunk.len();
+    }
+    cuts
+  }
+
+  #[test]
+  fn splits_at_pauses_after_min_length() {
+    // the short pause at 1 s is too early; the long ones after 3 s and 6 s split
+    let samples = tone(
+      16000,
+      &[(0.3, 1000), (0.0, 1000), (0.3, 1000), (0.0, 1000), (0.3, 2000), (0.0, 1000)],
+    );
+    let cuts = split_ms(&samples, 16000, 2000, 30000);
+    assert_eq!(cuts.len(), 2, "cuts {cuts:?}");
+    // each lands one hangover (800 ms) into the pause, within a frame
+    assert!((3800..=3820).contains(&cuts[0]), "cuts {cuts:?}");
+    assert!((6800..=6820).contains(&cuts[1]), "cuts {cuts:?}");
+  }
+
+  #[test]
+  fn splits_continuous_audio_at_max_length() {
+    let samples = tone(16000, &[(0.3, 2500)]);
+    assert_eq!(split_ms(&samples, 16000, 500, 1000), vec![1000, 2000]);
+  }
+
+  #[test]
+  fn splits_wav_into_chunks_at_pauses() {
+    let (_dir, path) = fixture(
+      "split",
+      16000,
+      &[(0.0, 500), (0.3, 2500), (0.0, 1000), (0.3, 2500), (0.0, 1000), (0.3, 4500)],
+    );
+    let chunk_path = |i: usize| path.with_extension(format!("chunk{i}.wav"));
+    let chunks = split_wav(&path, 4000, VAD, chunk_path).unwrap();
+    let lengths: Vec<u64> = chunks.iter().map(|c| c.stats.duration_ms).collect();
+    let spec = hound::WavReader::open(&chunks[0].path).unwrap().spec();
+    let total: u32 = chunks
+      .iter()
+      .map(|c| hound::WavReader::open(&c.path).unwrap().duration())
+      .sum();
+
+    // cuts one hangover into each pause (3.8 s, 7.3 s), then the long tone cut at 4 s
+    assert_eq!(lengths.len(), 4, "lengths {lengths:?}");
+    assert!((3800..=3820).contains(&lengths[0]), "lengths {lengths:?}");
+    assert!((3480..=3520).contains(&lengths[1]), "lengths {lengths:?}");
+    assert!(lengths.iter().all(|&ms| ms <= 4000), "lengths {lengths:?}");
+    assert_eq!((spec.channels, spec.sample_rate), (1, 16000));
+    assert_eq!(total, 12000 * 16);
+  }
+
+  #[test]
+  fn vad_stops_after_speech_then_silence() {
+    let (_dir, path) = fixture("vad-speech", 16000, &[(0.0, 500), (0.3, 1500), (0.0, 2000)]);
+    let end = vad_end_ms(&path, VAD).expect("should auto-stop");
+    // speech ends at 2000 ms; stop lands one hangover later, within a chunk or two
+    assert!((2800..=2900).contains(&end), "stopped at {end} ms");
+  }
+
+  #[test]
+  fn vad_ignores_leading_silence() {
+    let (_dir, path) = fixture("vad-silent", 48000, &[(0.0, 3000)]);
+    assert_eq!(vad_end_ms(&path, VAD), None);
+  }
+
+  #[test]
+  fn vad_hangover_bridges_short_pauses() {
+    let (_dir, path) = fixture("vad-pause", 16000, &[(0.3, 1000), (0.0, 500), (0.3, 1000), (0.0, 1500)]);
+    let end = vad_end_ms(&path, VAD).expect("should auto-stop");
+    assert!(end > 2500, "stopped during the pause at {end} ms");
+  }
+
+  #[test]
+  fn vad_ignores_clicks() {
+    // a 20 ms click is shorter than the onset
+    let (_dir, path) = fixture("vad-click", 16000, &[(0.0, 500), (0.5, 20), (0.0, 3000)]);
+    assert_eq!(vad_end_ms(&path, VAD), None);
+  }
+
+  #[test]
+  fn vad_tracks_noise_floor() {
+    // steady hum above the fixed threshold is learned as background, not speech
+    let (_dir, path) = fixture("vad-hum", 16000, &[(0.02, 1000), (0.3, 1500), (0.02, 2000)]);
+    let end = vad_end_ms(&path, VAD).expect("should auto-stop over hum");
+    assert!((3300..=3400).contains(&end), "stopped at {end} ms");
+  }
+}
@@ -0,0 +1,280 @@
+// src-tauri/src/chunks.rs
+//
+// Recordings over a provider's limits. Google's synchronous recognize takes
+// about a minute of audio and OpenAI takes 25 MB per upload, so anything longer
+// is cut at pauses into chunks that fit. The chunks are transcribed a few at a
+// time and their text is joined in order.
+use serde::Serialize;
+use std::{
+  future::Future,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+};
+use tauri::Emitter;
+use tokio::sync::Semaphore;
+
+use crate::{audio, get_settings, retention, TranscribeError};
+
+// uploads in flight at once for one recording
+const MAX_PARALLEL_CHUNKS: usize = 3;
+// a shorter pause than segments use, since chunks only need a clean place to cut
+const CHUNK_PAUSE_MS: u32 = 300;
+
+/// Payload of the `transcription-chunk` event, sent as each chunk finishes.
+#[derive(Serialize, Clone)]
+pub struct ChunkTranscribed {
+  pub recording: String,
+  pub index: usize,
+  pub count: usize,
+  // chunks finished so far, including this one; they finish out of order
+  pub completed: usize,
+}
+
+/// What one request to a provider may carry.
+pub struct ProviderLimits {
+  pub max_ms: u64,
+  pub max_bytes: u64,
+}
+
+pub fn limits(provider: &str) -> ProviderLimits {
+  match provider {
+    // 60 s for speech:recognize, and 10 MB of request once base64 adds a third
+    "google" => ProviderLimits {
+      max_ms: 55_000,
+      max_bytes: 7 * 1024 * 1024,
+    },
+    // 25 MB per file, less room for the multipart envelope
+    _ => ProviderLimits {
+      max_ms: u64::MAX,
+      max_bytes: 24 * 1024 * 1024,
+    },
+  }
+}
+
+/// Longest chunk of `spec` audio that fits `limits`. Sized as uncompressed
+/// WAV, so it holds whatever the upload format.
+fn max_chunk_ms(limits: &ProviderLimits, spec: hound::WavSpec) -> u64 {
+  let bytes_per_second = spec.sample_rate as u64 * spec.channels as u64 * (spec.bits_per_sample as u64 / 8);
+  let by_size = limits.max_bytes * 1000 / bytes_per_second.max(1);
+  limits.max_ms.min(by_size)
+}
+
+/// File for chunk `index` of `recording`.
+pub fn chunk_path(recording: &Path, index: usize) -> PathBuf {
+  retention::work_file_path(recording, "chunk", index)
+}
+
+/// Transcribe `path` with `request`, splitting it first when it is over
+/// `provider`'s limits. Recordings that fit go straight through.
+pub async fn transcribe<F, Fut>(
+  app: &tauri::AppHandle,
+  provider: &str,
+  path: &Path,
+  request: F,
+) -> Result<String, TranscribeError>
+where
+  F: Fn(PathBuf) -> Fut,
+  Fut: Future<Output = Result<String, TranscribeError>> + Send + 'static,
+{
+  let reader = hound::WavReader::open(path).map_err(|e| format!("wav open: {e}"))?;
+  let spec = reader.spec();
+  let duration_ms = reader.duration() as u64 * 1000 / spec.sample_rate.max(1) as u64;
+  drop(reader);
+  let max_ms = max_chunk_ms(&limits(provider), spec);
+  if duration_ms <= max_ms {
+    return request(path.to_path_buf()).await;
+  }
+
+  let settings = get_settings(app.clone())?;
+  let vad = audio::VadConfig {
+    threshold_db: settings.vad_threshold_db,
+    silence_ms: CHUNK_PAUSE_MS,
+  };
+  let recording = path.to_path_buf();
+  let split = tauri::async_runtime::spawn_blocking(move || {
+    let result = audio::split_wav(&recording, max_ms as u32, vad, |i| chunk_path(&recording, i));
+    if result.is_err() {
+      // chunks written before the failure
+      let mut i = 0;
+      while std::fs::remove_file(chunk_path(&recording, i)).is_ok() {
+        i += 1;
+      }
+    }
+    result
+  })
+  .await
+  .map_err(|e| format!("split task: {e}"))??;
+  let count = split.len();
+  eprintln!(
+    "Chunks: {} ms is over {}'s {} ms limit, split into {}",
+    duration_ms, provider, max_ms, count
+  );
+
+  let (app, recording) = (app.clone(), path.to_string_lossy().to_string());
+  transcribe_chunks(&split, settings.min_speech_db, request, move |index, completed| {
+    let _ = app.emit_to(
+      "panel",
+      "transcription-chunk",
+      ChunkTranscribed {
+        recording: recording.clone(),
+        index,
+        count,
+        completed,
+      },
+    );
+  })
+  .await
+}
+
+/// Transcribe `split` with `request`, a few chunks at a time, and join the
+/// text in order. Chunks in which nothing was heard add no text; any other
+/// failure stops the rest. `on_done(index, completed)` runs as each finishes.
+async fn transcribe_chunks<F, Fut>(
+  split: &[audio::WavChunk],
+  min_speech_db: f32,
+  request: F,
+  on_done: impl Fn(usize, usize) + Clone + Send + 'static,
+) -> Result<String, TranscribeError>
+where
+  F: Fn(PathBuf) -> Fut,
+  Fut: Future<Output = Result<String, TranscribeError>> + Send + 'static,
+{
+  let permits = Arc::new(Semaphore::new(MAX_PARALLEL_CHUNKS));
+  let completed = Arc::new(AtomicUsize::new(0));
+  let mut tasks = Vec::with_capacity(split.len());
+  for (index, chunk) in split.iter().enumerate() {
+    // chunks without a loud enough frame are not worth an upload, as in the pre-flight check
+    let upload = (chunk.stats.loudest_frame_db >= min_speech_db).then(|| request(chunk.path.clone()));
+    let (permits, completed, on_done) = (permits.clone(), completed.clone(), on_done.clone());
+    let chunk_file = chunk.path.clone();
+    tasks.push(tauri::async_runtime::spawn(async move {
+      let _permit = permits.acquire_owned().await.map_err(|e| format!("chunk queue: {e}"))?;
+      let result = match upload {
+        Some(upload) => upload.await,
+        None => Ok(String::new()),
+      };
+      let _ = std::fs::remove_file(&chunk_file);
+      let text = match result {
+        Ok(text) => text.trim().to_string(),
+        Err(TranscribeError::NothingHeard(_)) => String::new(),
+        Err(e) => return Err(e),
+      };
+      on_done(index, completed.fetch_add(1, Ordering::SeqCst) + 1);
+      Ok(text)
+    }));
+  }
+
+  let mut parts = Vec::new();
+  let mut pending = tasks.into_iter().zip(split).enumerate();
+  while let Some((index, (task, _))) = pending.next() {
+    let result = match task.await {
+      Ok(result) => result,
+      Err(e) => Err(format!("{e}").into()),
+    };
+    match result {
+      Ok(text) if text.is_empty() => {}
+      Ok(text) => parts.push(text),
+      Err(e) => {
+        // the rest would be thrown away; stop them and clean up
+        for (_, (task, chunk)) in pending {
+          task.abort();
+          let _ = std::fs::remove_file(&chunk.path);
+        }
+        return Err(match e {
+          TranscribeError::Failed(message) => TranscribeError::Failed(format!("chunk {}: {message}", index + 1)),
+          other => other,
+        });
+      }
+    }
+  }
+  if parts.is_empty() {
+    return Err(TranscribeError::NothingHeard("Nothing was heard in any chunk".into()));
+  }
+  Ok(parts.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// `count` chunks of a recording; `quiet` ones have nothing loud enough to send.
+  fn chunks(count: usize, quiet: &[usize]) -> Vec<audio::WavChunk> {
+    (0..count)
+      .map(|i| audio::WavChunk {
+        path: chunk_path(Path::new("/nonexistent/dictation-test.wav"), i),
+        stats: audio::RecordingStats {
+          loudest_frame_db: if quiet.contains(&i) { -70.0 } else { -20.0 },
+          ..Default::default()
+        },
+      })
+      .collect()
+  }
+
+  fn index_of(path: &Path) -> usize {
+    let stem = path.file_stem().unwrap().to_string_lossy();
+    stem.rsplit('-').next().unwrap().parse().unwrap()
+  }
+
+  #[test]
+  fn names_chunks_after_the_recording() {
+    assert_eq!(
+      chunk_path(Path::new("/cache/dictation-123.wav"), 7),
+      Path::new("/cache/chunk-dictation-123-007.wav")
+    );
+  }
+
+  #[test]
+  fn chunks_without_speech_add_no_text() {
+    let sent = Arc::new(AtomicUsize::new(0));
+    let finished = Arc::new(AtomicUsize::new(0));
+    let (sent_count, finished_count) = (sent.clone(), finished.clone());
+    let request = move |path: PathBuf| {
+      sent_count.fetch_add(1, Ordering::SeqCst);
+      async move {
+        match index_of(&path) {
+          // the provider found no speech, e.g. breathing or background noise
+          1 => Err(TranscribeError::NothingHeard("No speech detected in audio.".into())),
+          i => Ok(format!(" part{i} ")),
+        }
+      }
+    };
+    let text = tauri::async_runtime::block_on(transcribe_chunks(
+      &chunks(5, &[3]),
+      -50.0,
+      request,
+      move |_, _| {
+        finished_count.fetch_add(1, Ordering::SeqCst);
+      },
+    ))
+    .unwrap();
+    assert_eq!(text, "part0 part2 part4");
+    // the quiet chunk is never uploaded, but still counts as done
+    assert_eq!(sent.load(Ordering::SeqCst), 4);
+    assert_eq!(finished.load(Ordering::SeqCst), 5);
+  }
+
+  #[test]
+  fn nothing_heard_anywhere_is_nothing_heard() {
+    let request = |_: PathBuf| async { Err(TranscribeError::NothingHeard("No speech detected in audio.".into())) };
+    let result = tauri::async_runtime::block_on(transcribe_chunks(&chunks(3, &[]), -50.0, request, |_, _| {}));
+    assert!(matches!(result, Err(TranscribeError::NothingHeard(_))));
+  }
+
+  #[test]
+  fn a_failed_chunk_fails_the_recording() {
+    let request = |path: PathBuf| async move {
+      match index_of(&path) {
+        2 => Err(TranscribeError::Failed("quota exceeded".into())),
+        i => Ok(format!("part{i}")),
+      }
+    };
+    let result = tauri::async_runtime::block_on(transcribe_chunks(&chunks(4, &[]), -50.0, request, |_, _| {}));
+    match result {
+      Err(TranscribeError::Failed(message)) => assert_eq!(message, "chunk 3: quota exceeded"),
+      other => panic!("expected a failure, got {other:?}"),
+    }
+  }
+}
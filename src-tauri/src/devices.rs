@@ -0,0 +1,544 @@
+// src-tauri/src/devices.rs
+//
+// Input device discovery: what each device reports it can capture, picking a
+// device from the user's ordered preferences, finding a source for what the
+// system is playing, and turning a saved config choice back into a cpal
+// stream config.
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::{Deserialize, Serialize};
+
+/// One concrete capture format, as shown in and saved from the Settings page.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DeviceConfig {
+  pub channels: u16,
+  pub sample_rate: u32,
+  // cpal's name for the format: "i16", "f32", ...
+  pub sample_format: String,
+}
+
+/// A range of configs a device supports (one channel count and format).
+#[derive(Serialize, Clone, Debug)]
+pub struct SupportedConfigRange {
+  pub channels: u16,
+  pub min_sample_rate: u32,
+  pub max_sample_rate: u32,
+  pub sample_format: String,
+}
+
+#[derive(Serialize)]
+pub struct InputDevice {
+  pub name: String,
+  pub is_default: bool,
+  // backend the device was enumerated from, e.g. "ALSA", "WASAPI", "CoreAudio"
+  pub host: String,
+  pub default_config: Option<DeviceConfig>,
+  pub supported_configs: Vec<SupportedConfigRange>,
+  // captures system output (a monitor/loopback source) rather than a microphone
+  pub is_loopback: bool,
+}
+
+/// An audio backend compiled into this build (`cpal::available_hosts()`).
+#[derive(Serialize)]
+pub struct AudioHost {
+  // cpal's host name, saved in settings: "ALSA", "JACK", "WASAPI", ...
+  pub id: String,
+  pub is_default: bool,
+}
+
+/// Key of `device` on `host` (cpal's host name) in the `input_configs`
+/// setting. The same name can mean different hardware under ALSA and JACK, so
+/// a format saved under one host never applies to the other.
+pub fn config_key(host: &str, device: &str) -> String {
+  format!("{host}/{device}")
+}
+
+pub fn list_hosts() -> Vec<AudioHost> {
+  let default_id = cpal::default_host().id();
+  cpal::available_hosts()
+    .into_iter()
+    .map(|id| AudioHost {
+      id: id.name().to_string(),
+      is_default: id == default_id,
+    })
+    .collect()
+}
+
+/// Host named `preferred` (case-insensitive), or the platform default when it
+/// is empty, not compiled in, or fails to initialise (e.g. no JACK server).
+pub fn host(preferred: &str) -> cpal::Host {
+  if preferred.is_empty() {
+    return cpal::default_host();
+  }
+  let id = cpal::available_hosts()
+    .into_iter()
+    .find(|id| id.name().eq_ignore_ascii_case(preferred));
+  match id.map(cpal::host_from_id) {
+    Some(Ok(host)) => host,
+    Some(Err(e)) => {
+      eprintln!("Warning: audio host '{}' unavailable ({}). Using the default host.", preferred, e);
+      cpal::default_host()
+    }
+    None => {
+      eprintln!("Warning: audio host '{}' not available in this build. Using the default host.", preferred);
+      cpal::default_host()
+    }
+  }
+}
+
+impl From<&cpal::SupportedStreamConfig> for DeviceConfig {
+  fn from(config: &cpal::SupportedStreamConfig) -> Self {
+    Self {
+      channels: config.channels(),
+      sample_rate: config.sample_rate().0,
+      sample_format: config.sample_format().to_string(),
+    }
+  }
+}
+
+fn parse_sample_format(name: &str) -> Option<cpal::SampleFormat> {
+  use cpal::SampleFormat::*;
+  [I8, I16, I32, I64, U8, U16, U32, U64, F32, F64]
+    .into_iter()
+    .find(|f| f.to_string() == name)
+}
+
+// lowercase name fragments of capture devices that carry what the system plays:
+// PulseAudio/PipeWire monitor sources, Windows "Stereo Mix", macOS virtual drivers
+const LOOPBACK_PATTERNS: &[&str] = &[
+  "monitor of",
+  ".monitor",
+  "loopback",
+  "stereo mix",
+  "what u hear",
+  "wave out mix",
+  "blackhole",
+  "soundflower",
+  "cable output",
+];
+
+/// Whether a capture device name looks like a monitor/loopback source.
+pub fn is_loopback_name(name: &str) -> bool {
+  let name = name.to_lowercase();
+  LOOPBACK_PATTERNS.iter().any(|p| name.contains(p))
+}
+
+/// WASAPI captures an output device in loopback mode when it is opened for
+/// input, so every playback device doubles as a system audio source.
+fn outputs_capture_as_loopback(host: &cpal::Host) -> bool {
+  host.id().name() == "WASAPI"
+}
+
+fn describe(device: &cpal::Device, name: String, is_default: bool, host: &str, output: bool) -> InputDevice {
+  let default_config = if output {
+    device.default_output_config()
+  } else {
+    device.default_input_config()
+  }
+  .ok()
+  .map(|c| DeviceConfig::from(&c));
+  // devices that are busy or unplugged may fail here; list them anyway
+  let ranges = |r: cpal::SupportedStreamConfigRange| SupportedConfigRange {
+    channels: r.channels(),
+    min_sample_rate: r.min_sample_rate().0,
+    max_sample_rate: r.max_sample_rate().0,
+    sample_format: r.sample_format().to_string(),
+  };
+  let supported_configs = if output {
+    device
+      .supported_output_configs()
+      .map(|configs| configs.map(ranges).collect())
+  } else {
+    device
+      .supported_input_configs()
+      .map(|configs| configs.map(ranges).collect())
+  }
+  .unwrap_or_default();
+  InputDevice {
+    is_default,
+    is_loopback: output || is_loopback_name(&name),
+    name,
+    host: host.to_string(),
+    default_config,
+    supported_configs,
+  }
+}
+
+/// Describe every input device of `host`, with its capabilities. On WASAPI the
+/// output devices are listed too, as loopback sources.
+pub fn list_input_devices(host: &cpal::Host) -> Result<Vec<InputDevice>, String> {
+  let default_name = host
+    .default_input_device()
+    .and_then(|d| d.name().ok())
+    .unwrap_or_default();
+  let host_name = host.id().name();
+
+  let mut devices: Vec<InputDevice> = host
+    .input_devices()
+    .map_err(|e| format!("list devices: {e}"))?
+    .filter_map(|device| {
+      let name = device.name().ok()?;
+      let is_default = name == default_name;
+      Some(describe(&device, name, is_default, host_name, false))
+    })
+    .collect();
+
+  if outputs_capture_as_loopback(host) {
+    let outputs = host
+      .output_devices()
+      .map_err(|e| format!("list output devices: {e}"))?
+      .filter_map(|device| {
+        let name = device.name().ok()?;
+        Some(describe(&device, name, false, host_name, true))
+      });
+    devices.extend(outputs);
+  }
+
+  Ok(devices)
+}
+
+/// How a preference matched a device name, best first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchKind {
+  Exact,
+  IgnoreCase,
+  Substring,
+  // every word of the preference appears in the name, ignoring short numbers
+  // such as the "(2- ...)" prefix Windows adds when a device is re-plugged
+  Words,
+}
+
+impl MatchKind {
+  fn describe(self) -> &'static str {
+    match self {
+      MatchKind::Exact => "exact match",
+      MatchKind::IgnoreCase => "name differs only in case",
+      MatchKind::Substring => "name contains",
+      MatchKind::Words => "name has the words of",
+    }
+  }
+}
+
+fn words(s: &str) -> Vec<String> {
+  s.split(|c: char| !c.is_alphanumeric())
+    .filter(|w| !w.is_empty())
+    // drop short numbers such as the "2" in "Microphone (2- USB Audio)"
+    .filter(|w| !(w.len() <= 2 && w.chars().all(|c| c.is_ascii_digit())))
+    .map(|w| w.to_lowercase())
+    .collect()
+}
+
+fn match_kind(preference: &str, name: &str) -> Option<MatchKind> {
+  if name == preference {
+    return Some(MatchKind::Exact);
+  }
+  let (pref_lower, name_lower) = (preference.to_lowercase(), name.to_lowercase());
+  if name_lower == pref_lower {
+    return Some(MatchKind::IgnoreCase);
+  }
+  if name_lower.contains(&pref_lower) {
+    return Some(MatchKind::Substring);
+  }
+  let pref_words = words(preference);
+  let name_words = words(name);
+  if !pref_words.is_empty() && pref_words.iter().all(|w| name_words.contains(w)) {
+    return Some(MatchKind::Words);
+  }
+  None
+}
+
+/// The device a microphone preference list picks from `names`: the first
+/// preference that matches anything, then the best match kind for it. A
+/// monitor/loopback source only wins when nothing else matches, unless the
+/// preference itself names one, so "Headset" never picks "Monitor of Headset".
+/// Ties go to the device enumerated first. Returns (preference, name) indices.
+fn rank_devices(preferences: &[&str], names: &[String]) -> Option<(usize, usize, MatchKind)> {
+  preferences.iter().enumerate().find_map(|(p, preference)| {
+    let wants_loopback = is_loopback_name(preference);
+    names
+      .iter()
+      .enumerate()
+      .filter_map(|(n, name)| match_kind(preference, name).map(|kind| (n, kind, name)))
+      .min_by_key(|&(_, kind, name)| (!wants_loopback && is_loopback_name(name), kind))
+      .map(|(n, kind, _)| (p, n, kind))
+  })
+}
+
+/// Payload of the `input-device` event: which device a recording uses and why.
+#[derive(Serialize, Clone, Debug)]
+pub struct DeviceChoice {
+  pub device: String,
+  pub reason: String,
+  // index into the preference list that matched; None = system default
+  pub preference: Option<usize>,
+  // preferences were set but none of them is connected
+  pub fallback: bool,
+}
+
+/// Open the first preference that matches a connected device, best match
+/// kind first, falling back to the host's default input device.
+pub fn choose_input_device(
+  host: &cpal::Host,
+  preferences: &[String],
+) -> Result<(cpal::Device, DeviceChoice), String> {
+  let preferences: Vec<&str> = preferences
+    .iter()
+    .map(|p| p.trim())
+    .filter(|p| !p.is_empty())
+    .collect();
+
+  if !preferences.is_empty() {
+    let (names, devices): (Vec<String>, Vec<cpal::Device>) = host
+      .input_devices()
+      .map_err(|e| format!("list devices: {e}"))?
+      .filter_map(|d| d.name().ok().map(|n| (n, d)))
+      .unzip();
+
+    if let Some((index, n, kind)) = rank_devices(&preferences, &names) {
+      let preference = preferences[index];
+      let reason = match kind {
+        MatchKind::Exact => format!("preference #{}: {}", index + 1, kind.describe()),
+        _ => format!("preference #{}: {} \"{}\"", index + 1, kind.describe(), preference),
+      };
+      return Ok((
+        devices[n].clone(),
+        DeviceChoice {
+          device: names[n].clone(),
+          reason,
+          preference: Some(index),
+          fallback: false,
+        },
+      ));
+    }
+  }
+
+  let device = host
+    .default_input_device()
+    .ok_or("No default input device (mic)".to_string())?;
+  let fallback = !preferences.is_empty();
+  let reason = if fallback {
+    format!("none of {} preferred devices is connected; using the system default", preferences.len())
+  } else {
+    "system default".to_string()
+  };
+  Ok((
+    device.clone(),
+    DeviceChoice {
+      device: device.name().unwrap_or_else(|_| "Unknown".to_string()),
+      reason,
+      preference: None,
+      fallback,
+    },
+  ))
+}
+
+/// Device named exactly `name` on `host`; empty = the default device.
+pub fn find_device(host: &cpal::Host, name: &str) -> Result<cpal::Device, String> {
+  if name.is_empty() {
+    return host
+      .default_input_device()
+      .ok_or("No default input device (mic)".to_string());
+  }
+  host
+    .input_devices()
+    .map_err(|e| format!("list devices: {e}"))?
+    .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+    .ok_or_else(|| format!("Input device not found: {name}"))
+}
+
+/// Devices that can capture system audio: inputs that look like monitor or
+/// loopback sources, plus the output devices themselves on WASAPI.
+fn loopback_candidates(host: &cpal::Host, all_inputs: bool) -> Result<Vec<(String, cpal::Device)>, String> {
+  let mut candidates: Vec<(String, cpal::Device)> = host
+    .input_devices()
+    .map_err(|e| format!("list devices: {e}"))?
+    .filter_map(|d| d.name().ok().map(|n| (n, d)))
+    .filter(|(name, _)| all_inputs || is_loopback_name(name))
+    .collect();
+  if outputs_capture_as_loopback(host) {
+    let outputs = host
+      .output_devices()
+      .map_err(|e| format!("list output devices: {e}"))?
+      .filter_map(|d| d.name().ok().map(|n| (n, d)));
+    candidates.extend(outputs);
+  }
+  Ok(candidates)
+}
+
+/// Pick the system audio source: the device matching `preference` (any input,
+/// so a source the name heuristic misses can still be chosen), otherwise the
+/// monitor of the default output device, otherwise the first loopback source.
+pub fn choose_loopback_device(
+  host: &cpal::Host,
+  preference: &str,
+) -> Result<(cpal::Device, DeviceChoice), String> {
+  let preference = preference.trim();
+  if !preference.is_empty() {
+    let best = loopback_candidates(host, true)?
+      .into_iter()
+      .filter_map(|(name, device)| match_kind(preference, &name).map(|kind| (kind, name, device)))
+      .min_by_key(|(kind, _, _)| *kind);
+    if let Some((kind, name, device)) = best {
+      let reason = match kind {
+        MatchKind::Exact => format!("system audio: {}", kind.describe()),
+        _ => format!("system audio: {} \"{}\"", kind.describe(), preference),
+      };
+      return Ok((
+        device,
+        DeviceChoice {
+          device: name,
+          reason,
+          preference: Some(0),
+          fallback: false,
+        },
+      ));
+    }
+  }
+
+  let mut candidates = loopback_candidates(host, false)?;
+  if candidates.is_empty() {
+    return Err(
+      "No system audio source found. Enable a monitor or loopback device \
+       (e.g. Stereo Mix, BlackHole) or pick one in Settings."
+        .into(),
+    );
+  }
+  // PulseAudio names monitors "Monitor of <sink>"; WASAPI lists the sink itself
+  let default_output = host
+    .default_output_device()
+    .and_then(|d| d.name().ok())
+    .map(|n| n.to_lowercase());
+  let index = default_output
+    .and_then(|out| candidates.iter().position(|(name, _)| name.to_lowercase().contains(&out)))
+    .unwrap_or(0);
+  let (name, device) = candidates.swap_remove(index);
+  let fallback = !preference.is_empty();
+  let reason = if fallback {
+    format!("system audio source \"{preference}\" is not connected; using a detected loopback source")
+  } else {
+    "system audio: detected loopback source".to_string()
+  };
+  Ok((
+    device,
+    DeviceChoice {
+      device: name,
+      reason,
+      preference: None,
+      fallback,
+    },
+  ))
+}
+
+/// Find the supported config matching `wanted` exactly.
+pub fn find_config(
+  device: &cpal::Device,
+  wanted: &DeviceConfig,
+) -> Result<cpal::SupportedStreamConfig, String> {
+  let format = parse_sample_format(&wanted.sample_format)
+    .ok_or_else(|| format!("Unknown sample format: {}", wanted.sample_format))?;
+  device
+    .supported_input_configs()
+    .map_err(|e| format!("supported input configs: {e}"))?
+    .find(|r| {
+      r.channels() == wanted.channels
+        && r.sample_format() == format
+        && (r.min_sample_rate().0..=r.max_sample_rate().0).contains(&wanted.sample_rate)
+    })
+    .map(|r| r.with_sample_rate(cpal::SampleRate(wanted.sample_rate)))
+    .ok_or_else(|| {
+      format!(
+        "Device does not support {} ch / {} Hz / {}",
+        wanted.channels, wanted.sample_rate, wanted.sample_format
+      )
+    })
+}
+
+/// Config to open `device` with: the saved choice if the device supports it,
+/// otherwise the device default. A WASAPI output device opened for loopback
+/// has no input configs and uses its output format.
+pub fn stream_config(
+  device: &cpal::Device,
+  wanted: Option<&DeviceConfig>,
+) -> Result<cpal::SupportedStreamConfig, String> {
+  if let Some(wanted) = wanted {
+    match find_config(device, wanted) {
+      Ok(config) => return Ok(config),
+      Err(e) => eprintln!("Warning: {}. Using the device default config.", e),
+    }
+  }
+  device
+    .default_input_config()
+    .or_else(|e| device.default_output_config().map_err(|_| e))
+    .map_err(|e| format!("default input config: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pick(preferences: &[&str], names: &[&str]) -> Option<(usize, String, MatchKind)> {
+    let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+    rank_devices(preferences, &names).map(|(p, n, kind)| (p, names[n].clone(), kind))
+  }
+
+  fn chose(preference: usize, name: &str, kind: MatchKind) -> Option<(usize, String, MatchKind)> {
+    Some((preference, name.to_string(), kind))
+  }
+
+  #[test]
+  fn earlier_preference_wins_over_better_match() {
+    let names = ["MacBook Pro Microphone", "Jabra Evolve2 65"];
+    let preferences = ["jabra", "MacBook Pro Microphone"];
+    assert_eq!(pick(&preferences, &names), chose(0, "Jabra Evolve2 65", MatchKind::Substring));
+    // the built-in mic only once the headset is unplugged
+    assert_eq!(
+      pick(&preferences, &names[..1]),
+      chose(1, "MacBook Pro Microphone", MatchKind::Exact)
+    );
+  }
+
+  #[test]
+  fn ranks_exact_then_case_then_substring_then_words() {
+    let names = ["USB Mic Pro", "usb mic", "USB Mic", "Mic (USB)"];
+    assert_eq!(pick(&["USB Mic"], &names), chose(0, "USB Mic", MatchKind::Exact));
+    assert_eq!(pick(&["usb MIC"], &names), chose(0, "usb mic", MatchKind::IgnoreCase));
+    assert_eq!(pick(&["Mic Pro"], &names), chose(0, "USB Mic Pro", MatchKind::Substring));
+    assert_eq!(pick(&["pro usb"], &names), chose(0, "USB Mic Pro", MatchKind::Words));
+  }
+
+  #[test]
+  fn matches_windows_replug_names() {
+    // Windows numbers a re-plugged device: "Microphone (2- USB Audio Device)"
+    let names = ["Microphone (Realtek Audio)", "Microphone (2- USB Audio Device)"];
+    assert_eq!(
+      pick(&["Microphone (USB Audio Device)"], &names),
+      chose(0, "Microphone (2- USB Audio Device)", MatchKind::Words)
+    );
+  }
+
+  #[test]
+  fn never_prefers_a_monitor_source_for_a_mic() {
+    // enumerated first and the same match kind, but a monitor is not a mic
+    let names = ["Monitor of Headset Stereo", "Headset Mono"];
+    assert_eq!(pick(&["Headset"], &names), chose(0, "Headset Mono", MatchKind::Substring));
+    // even a closer match: PipeWire names monitors "<sink>.monitor"
+    assert_eq!(
+      pick(&["headset"], &["headset.monitor", "USB Headset Mic"]),
+      chose(0, "USB Headset Mic", MatchKind::Substring)
+    );
+    // a monitor is still used when it is the only match, or is asked for
+    assert_eq!(
+      pick(&["Headset"], &["Monitor of Headset Stereo"]),
+      chose(0, "Monitor of Headset Stereo", MatchKind::Substring)
+    );
+    assert_eq!(
+      pick(&["Monitor of Headset"], &["Headset Mono", "Monitor of Headset Stereo"]),
+      chose(0, "Monitor of Headset Stereo", MatchKind::Substring)
+    );
+  }
+
+  #[test]
+  fn nothing_matches() {
+    assert_eq!(pick(&["Blue Yeti"], &["Built-in Microphone"]), None);
+    assert_eq!(pick(&[], &["Built-in Microphone"]), None);
+  }
+}
@@ -0,0 +1,388 @@
+// src-tauri/src/encode.rs
+//
+// Compressed upload encoding. Recordings stay 16-bit PCM WAV on disk; just
+// before upload they can be re-encoded as FLAC (lossless, about half the size
+// for speech) or Ogg/Opus (lossy, about a twentieth). FLAC is pure Rust;
+// Opus needs libopus and is only built with the `opus` feature.
+use std::path::Path;
+
+/// Audio format sent to the provider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadFormat {
+  Wav,
+  Flac,
+  Opus,
+}
+
+impl UploadFormat {
+  pub fn from_setting(format: &str) -> Self {
+    match format {
+      "flac" => UploadFormat::Flac,
+      "opus" => UploadFormat::Opus,
+      _ => UploadFormat::Wav,
+    }
+  }
+
+  /// Name in `AppSettings.upload_format`.
+  pub fn setting(self) -> &'static str {
+    match self {
+      UploadFormat::Wav => "wav",
+      UploadFormat::Flac => "flac",
+      UploadFormat::Opus => "opus",
+    }
+  }
+
+  /// File name for multipart uploads; OpenAI goes by the extension.
+  pub fn file_name(self) -> &'static str {
+    match self {
+      UploadFormat::Wav => "audio.wav",
+      UploadFormat::Flac => "audio.flac",
+      UploadFormat::Opus => "audio.ogg",
+    }
+  }
+
+  pub fn mime(self) -> &'static str {
+    match self {
+      UploadFormat::Wav => "audio/wav",
+      UploadFormat::Flac => "audio/flac",
+      UploadFormat::Opus => "audio/ogg",
+    }
+  }
+
+  /// `config.encoding` for Google Speech-to-Text.
+  pub fn google_encoding(self) -> &'static str {
+    match self {
+      UploadFormat::Wav => "LINEAR16",
+      UploadFormat::Flac => "FLAC",
+      UploadFormat::Opus => "OGG_OPUS",
+    }
+  }
+
+  /// Whether this build can produce the format.
+  pub fn available(self) -> bool {
+    self != UploadFormat::Opus || cfg!(feature = "opus")
+  }
+}
+
+/// Formats this build can encode, for the Settings picker.
+pub fn available_formats() -> Vec<UploadFormat> {
+  [UploadFormat::Wav, UploadFormat::Flac, UploadFormat::Opus]
+    .into_iter()
+    .filter(|f| f.available())
+    .collect()
+}
+
+/// Formats each provider accepts. Google takes LINEAR16, FLAC and OGG_OPUS;
+/// OpenAI takes wav, flac and ogg among others.
+pub fn accepted_formats(provider: &str) -> &'static [UploadFormat] {
+  match provider {
+    "google" => &[UploadFormat::Wav, UploadFormat::Flac, UploadFormat::Opus],
+    "openai" => &[UploadFormat::Wav, UploadFormat::Flac, UploadFormat::Opus],
+    _ => &[UploadFormat::Wav],
+  }
+}
+
+/// The format to upload in: the configured one when the provider accepts it,
+/// otherwise WAV. A format this build can't encode is an error, not a quiet
+/// substitute.
+pub fn upload_format(provider: &str, setting: &str) -> Result<UploadFormat, String> {
+  let wanted = UploadFormat::from_setting(setting);
+  if !wanted.available() {
+    return Err(format!(
+      "{} uploads are not available in this build; choose another upload format in Settings",
+      wanted.setting()
+    ));
+  }
+  Ok(if accepted_formats(provider).contains(&wanted) {
+    wanted
+  } else {
+    UploadFormat::Wav
+  })
+}
+
+/// An encoded recording, ready to upload.
+pub struct Encoded {
+  pub bytes: Vec<u8>,
+  pub format: UploadFormat,
+  /// Rate of the encoded audio; Opus may resample.
+  pub sample_rate: u32,
+  pub channels: u16,
+}
+
+/// Read a 16-bit WAV recording and encode it as `format`.
+pub fn encode_for_upload(path: &Path, format: UploadFormat) -> Result<Encoded, String> {
+  if format == UploadFormat::Wav {
+    let spec = hound::WavReader::open(path)
+      .map_err(|e| format!("wav open: {e}"))?
+      .spec();
+    let bytes = std::fs::read(path).map_err(|e| format!("read audio: {e}"))?;
+    return Ok(Encoded {
+      bytes,
+      format,
+      sample_rate: spec.sample_rate,
+      channels: spec.channels,
+    });
+  }
+
+  let mut reader = hound::WavReader::open(path).map_err(|e| format!("wav open: {e}"))?;
+  let spec = reader.spec();
+  if spec.bits_per_sample != 16 || spec.sample_format != hound::SampleFormat::Int {
+    return Err(format!("cannot encode {}-bit audio", spec.bits_per_sample));
+  }
+  let samples = reader
+    .samples::<i16>()
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("wav read: {e}"))?;
+
+  match format {
+    UploadFormat::Flac => Ok(Encoded {
+      bytes: encode_flac(&samples, spec.channels, spec.sample_rate)?,
+      format,
+      sample_rate: spec.sample_rate,
+      channels: spec.channels,
+    }),
+    #[cfg(feature = "opus")]
+    UploadFormat::Opus => opus::encode(&samples, spec.channels, spec.sample_rate),
+    _ => Err(format!("{format:?} encoding is not available in this build")),
+  }
+}
+
+/// Samples per FLAC frame; flacenc's default, and what libFLAC uses at 16 kHz and up.
+const FLAC_BLOCK: usize = 4096;
+
+/// Encode interleaved 16-bit samples as a FLAC stream, padded with silence to
+/// a whole number of frames.
+pub fn encode_flac(samples: &[i16], channels: u16, sample_rate: u32) -> Result<Vec<u8>, String> {
+  use flacenc::{component::BitRepr, error::Verify};
+
+  let mut config = flacenc::config::Encoder::default();
+  config.block_size = FLAC_BLOCK;
+  let config = config
+    .into_verified()
+    .map_err(|(_, e)| format!("flac config: {e}"))?;
+  // flacenc reuses its frame buffer, so a short last frame would end with
+  // samples of the one before; pad it ourselves so the tail is silence
+  let frame_len = FLAC_BLOCK * channels.max(1) as usize;
+  let mut samples: Vec<i32> = samples.iter().map(|&s| s as i32).collect();
+  samples.resize(samples.len().div_ceil(frame_len) * frame_len, 0);
+  let source = flacenc::source::MemSource::from_samples(&samples, channels as usize, 16, sample_rate as usize);
+  let stream = flacenc::encode_with_fixed_block_size(&config, source, FLAC_BLOCK)
+    .map_err(|e| format!("flac encode: {e:?}"))?;
+  let mut sink = flacenc::bitsink::ByteSink::new();
+  stream.write(&mut sink).map_err(|e| format!("flac write: {e}"))?;
+  Ok(sink.into_inner())
+}
+
+#[cfg(feature = "opus")]
+mod opus {
+  use audiopus::{coder::Encoder, Application, Bitrate, Channels, SampleRate};
+  use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+  use super::{Encoded, UploadFormat};
+  use crate::audio;
+
+  // 20 ms packets
+  const FRAME_MS: u32 = 20;
+  // plenty for speech; Google recommends at least 16 kbps per channel
+  const BITRATE_PER_CHANNEL: i32 = 24_000;
+  // one logical stream per file, so any serial will do
+  const SERIAL: u32 = 0x6874_7970;
+
+  /// Encode interleaved 16-bit samples as Ogg/Opus. Rates Opus cannot take
+  /// (44.1 kHz, say) are resampled to 48 kHz.
+  pub fn encode(samples: &[i16], channels: u16, sample_rate: u32) -> Result<Encoded, String> {
+    let (mut samples, rate) = match SampleRate::try_from(sample_rate as i32) {
+      Ok(rate) => (samples.to_vec(), rate),
+      Err(_) => (resample(samples, channels, sample_rate, 48000)?, SampleRate::Hz48000),
+    };
+    let opus_rate = rate as u32;
+    let mut encoder = Encoder::new(
+      rate,
+      if channels > 1 { Channels::Stereo } else { Channels::Mono },
+      Application::Voip,
+    )
+    .map_err(|e| format!("opus encoder: {e}"))?;
+    encoder
+      .set_bitrate(Bitrate::BitsPerSecond(BITRATE_PER_CHANNEL * channels as i32))
+      .map_err(|e| format!("opus bitrate: {e}"))?;
+    // granule positions are always in 48 kHz samples
+    let scale = 48000 / opus_rate as u64;
+    let pre_skip = encoder.lookahead().map_err(|e| format!("opus lookahead: {e}"))? as u64 * scale;
+
+    let mut writer = PacketWriter::new(Vec::new());
+    let mut head = b"OpusHead".to_vec();
+    head.push(1);
+    head.push(channels as u8);
+    head.extend((pre_skip as u16).to_le_bytes());
+    head.extend(sample_rate.to_le_bytes());
+    head.extend(0i16.to_le_bytes());
+    head.push(0);
+    let vendor = b"hotkey-type";
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend((vendor.len() as u32).to_le_bytes());
+    tags.extend(vendor);
+    tags.extend(0u32.to_le_bytes());
+    for packet in [head, tags] {
+      writer
+        .write_packet(packet.into_boxed_slice(), SERIAL, PacketWriteEndInfo::EndPage, 0)
+        .map_err(|e| format!("ogg write: {e}"))?;
+    }
+
+    let frame_len = (opus_rate * FRAME_MS / 1000) as usize * channels as usize;
+    let total = (samples.len() / channels as usize) as u64 * scale;
+    // the encoder holds back its lookahead; feed that much silence so the end of the audio comes out too
+    samples.resize(samples.len() + (pre_skip / scale) as usize * channels as usize, 0);
+    let mut frame = vec![0i16; frame_len];
+    let mut packet = [0u8; 4000];
+    let mut position = 0u64;
+    let chunks = samples.chunks(frame_len).count();
+    for (i, chunk) in samples.chunks(frame_len).enumerate() {
+      // the last frame is padded with silence; the final granule trims it and the lookahead
+      frame.fill(0);
+      frame[..chunk.len()].copy_from_slice(chunk);
+      let len = encoder
+        .encode(&frame, &mut packet)
+        .map_err(|e| format!("opus encode: {e}"))?;
+      position += (frame_len / channels as usize) as u64 * scale;
+      let last = i + 1 == chunks;
+      // granules count decoded samples, pre-skip included; the last one says where audio ends
+      let (end, granule) = if last {
+        (PacketWriteEndInfo::EndStream, pre_skip + total)
+      } else {
+        (PacketWriteEndInfo::NormalPacket, position)
+      };
+      writer
+        .write_packet(packet[..len].to_vec().into_boxed_slice(), SERIAL, end, granule)
+        .map_err(|e| format!("ogg write: {e}"))?;
+    }
+
+    Ok(Encoded {
+      bytes: writer.into_inner(),
+      format: UploadFormat::Opus,
+      sample_rate: opus_rate,
+      channels,
+    })
+  }
+
+  fn resample(samples: &[i16], channels: u16, from: u32, to: u32) -> Result<Vec<i16>, String> {
+    let channels = channels.max(1) as usize;
+    let mut resampled: Vec<Vec<f32>> = Vec::with_capacity(channels);
+    for c in 0..channels {
+      let input: Vec<f32> = samples
+        .iter()
+        .skip(c)
+        .step_by(channels)
+        .map(|&s| s as f32 / 32768.0)
+        .collect();
+      let mut resampler = audio::MonoResampler::new(from, to)?;
+      let mut out = Vec::new();
+      resampler.process(&input, &mut out)?;
+      resampler.flush(&mut out)?;
+      resampled.push(out);
+    }
+    let frames = resampled.iter().map(|c| c.len()).min().unwrap_or(0);
+    Ok(
+      (0..frames)
+        .flat_map(|i| resampled.iter().map(move |c| audio::f32_to_i16(c[i])))
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, formats::FormatOptions, io::MediaSourceStream,
+    meta::MetadataOptions, probe::Hint,
+  };
+
+  fn decode_flac(bytes: Vec<u8>) -> (u32, usize, Vec<i16>) {
+    let stream = MediaSourceStream::new(Box::new(std::io::Cursor::new(bytes)), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("flac");
+    let mut format = symphonia::default::get_probe()
+      .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+      .unwrap()
+      .format;
+    let params = format.default_track().unwrap().codec_params.clone();
+    let mut decoder = symphonia::default::get_codecs()
+      .make(&params, &DecoderOptions::default())
+      .unwrap();
+    let channels = params.channels.unwrap().count();
+    let mut samples = Vec::new();
+    while let Ok(packet) = format.next_packet() {
+      let decoded = decoder.decode(&packet).unwrap();
+      let mut buf = SampleBuffer::<i16>::new(decoded.capacity() as u64, *decoded.spec());
+      buf.copy_interleaved_ref(decoded);
+      samples.extend_from_slice(buf.samples());
+    }
+    (params.sample_rate.unwrap(), channels, samples)
+  }
+
+  /// `decoded` is `samples` followed by nothing but the silence padding.
+  fn assert_lossless(decoded: &[i16], samples: &[i16]) {
+    assert_eq!(&decoded[..samples.len()], samples);
+    assert!(decoded[samples.len()..].iter().all(|&s| s == 0), "padding is not silence");
+  }
+
+  fn speechish(len: usize, channels: usize) -> Vec<i16> {
+    // a tone, digital silence, then a louder chirp; not a multiple of the block size
+    let mut seed = 1u32;
+    (0..len)
+      .flat_map(|i| {
+        let t = i as f32 / 16000.0;
+        let v = if i < len / 3 {
+          (t * 2.0 * std::f32::consts::PI * 220.0).sin() * 6000.0
+        } else if i < len / 2 {
+          0.0
+        } else {
+          (t * t * 2.0 * std::f32::consts::PI * 3000.0).sin() * 30000.0
+        };
+        (0..channels)
+          .map(|c| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let noise = if i < len / 3 || i >= len / 2 { (seed >> 16) as i32 % 64 - 32 } else { 0 };
+            (v as i32 / (c as i32 + 1) + noise).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+          })
+          .collect::<Vec<_>>()
+      })
+      .collect()
+  }
+
+  #[test]
+  fn flac_round_trips_losslessly() {
+    for channels in [1u16, 2] {
+      // past 127 frames, where frame numbers take two bytes
+      let samples = speechish(130 * FLAC_BLOCK + 123, channels as usize);
+      let bytes = encode_flac(&samples, channels, 16000).unwrap();
+      assert!(bytes.len() < samples.len() * 2, "{} bytes", bytes.len());
+      let (rate, decoded_channels, decoded) = decode_flac(bytes);
+      assert_eq!((rate, decoded_channels), (16000, channels as usize));
+      assert_lossless(&decoded, &samples);
+    }
+  }
+
+  #[test]
+  fn flac_handles_extremes_and_tiny_inputs() {
+    let samples: Vec<i16> = (0..FLAC_BLOCK + 7)
+      .map(|i| if i % 2 == 0 { i16::MAX } else { i16::MIN })
+      .collect();
+    assert_lossless(&decode_flac(encode_flac(&samples, 1, 48000).unwrap()).2, &samples);
+    assert_lossless(&decode_flac(encode_flac(&[5, -3, 9], 1, 8000).unwrap()).2, &[5, -3, 9]);
+  }
+
+  #[test]
+  fn unavailable_formats_are_reported() {
+    assert_eq!(upload_format("openai", "flac"), Ok(UploadFormat::Flac));
+    assert_eq!(upload_format("google", "wav"), Ok(UploadFormat::Wav));
+    assert_eq!(upload_format("other", "flac"), Ok(UploadFormat::Wav));
+    if cfg!(feature = "opus") {
+      assert_eq!(upload_format("google", "opus"), Ok(UploadFormat::Opus));
+      assert!(available_formats().contains(&UploadFormat::Opus));
+    } else {
+      assert!(upload_format("google", "opus").is_err());
+      assert_eq!(available_formats(), vec![UploadFormat::Wav, UploadFormat::Flac]);
+    }
+  }
+}
@@ -0,0 +1,294 @@
+// src-tauri/src/import.rs
+//
+// Importing audio files for transcription. Any format symphonia can decode
+// (WAV of any bit depth, FLAC, MP3, Ogg Vorbis, M4A/AAC, ALAC, AIFF, CAF) is
+// converted to the recorder's own format, 16-bit mono PCM at the configured
+// rate, so every provider can take it. Symphonia has no Opus decoder, so
+// Ogg/Opus goes through libopus, with the same `opus` feature as uploads.
+use std::path::Path;
+use symphonia::core::{
+  audio::SampleBuffer,
+  codecs::{DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS},
+  errors::Error as SymphoniaError,
+  formats::FormatOptions,
+  io::MediaSourceStream,
+  meta::MetadataOptions,
+  probe::Hint,
+};
+
+use crate::audio::{self, ChannelSelect};
+
+/// What was decoded from an imported file.
+#[derive(Debug)]
+pub struct Imported {
+  pub source_rate: u32,
+  pub channels: usize,
+  pub sample_rate: u32,
+  pub duration_ms: u64,
+}
+
+/// Mono 16-bit WAV output: decoded audio is downmixed, resampled and written
+/// one packet at a time, so long files never sit in memory whole.
+struct WavOut {
+  resampler: audio::MonoResampler,
+  writer: hound::WavWriter<std::io::BufWriter<std::fs::File>>,
+  sample_rate: u32,
+  mono: Vec<f32>,
+  resampled: Vec<f32>,
+  written: u64,
+}
+
+impl WavOut {
+  fn create(output: &Path, source_rate: u32, target_rate: u32) -> Result<Self, String> {
+    let sample_rate = audio::output_rate(source_rate, target_rate);
+    let spec = hound::WavSpec {
+      channels: 1,
+      sample_rate,
+      bits_per_sample: 16,
+      sample_format: hound::SampleFormat::Int,
+    };
+    Ok(WavOut {
+      resampler: audio::MonoResampler::new(source_rate, sample_rate)?,
+      writer: hound::WavWriter::create(output, spec).map_err(|e| format!("wav create: {e}"))?,
+      sample_rate,
+      mono: Vec::new(),
+      resampled: Vec::new(),
+      written: 0,
+    })
+  }
+
+  fn push(&mut self, interleaved: &[f32], channels: usize) -> Result<(), String> {
+    self.mono.clear();
+    self.mono.extend(
+      interleaved
+        .chunks(channels.max(1))
+        .map(|frame| audio::mix_frame(frame, ChannelSelect::Downmix)),
+    );
+    self.resampled.clear();
+    self.resampler.process(&self.mono, &mut self.resampled)?;
+    self.write_resampled()
+  }
+
+  fn write_resampled(&mut self) -> Result<(), String> {
+    for &s in &self.resampled {
+      self
+        .writer
+        .write_sample(audio::f32_to_i16(s))
+        .map_err(|e| format!("wav write: {e}"))?;
+    }
+    self.written += self.resampled.len() as u64;
+    Ok(())
+  }
+
+  /// Flush the resampler and finalize the file; returns the duration in ms.
+  fn finish(mut self) -> Result<u64, String> {
+    self.resampled.clear();
+    self.resampler.flush(&mut self.resampled)?;
+    self.write_resampled()?;
+    self.writer.finalize().map_err(|e| format!("wav finalize: {e}"))?;
+    if self.written == 0 {
+      return Err("The file contains no audio".into());
+    }
+    Ok(self.written * 1000 / self.sample_rate as u64)
+  }
+}
+
+/// Decode `input` and write it to `output` as a mono 16-bit WAV at
+/// `target_rate` (0 keeps the file's own rate, as for recordings).
+pub fn decode_to_wav(input: &Path, output: &Path, target_rate: u32) -> Result<Imported, String> {
+  let file = std::fs::File::open(input).map_err(|e| format!("open: {e}"))?;
+  let stream = MediaSourceStream::new(Box::new(file), Default::default());
+  let mut hint = Hint::new();
+  if let Some(ext) = input.extension().and_then(|e| e.to_str()) {
+    hint.with_extension(ext);
+  }
+  let probed = symphonia::default::get_probe()
+    .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+    .map_err(|e| format!("Unsupported or unreadable audio file: {e}"))?;
+  let mut format = probed.format;
+
+  let track = format
+    .tracks()
+    .iter()
+    .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+    .ok_or("No audio track in file")?;
+  if track.codec_params.codec == CODEC_TYPE_OPUS {
+    #[cfg(feature = "opus")]
+    return opus::decode_to_wav(input, output, target_rate);
+    #[cfg(not(feature = "opus"))]
+    return Err("Opus audio needs a build with the opus feature; convert the file to FLAC, MP3 or WAV first".into());
+  }
+  let track_id = track.id;
+  let source_rate = track
+    .codec_params
+    .sample_rate
+    .ok_or("Audio track has no sample rate")?;
+  let mut decoder = symphonia::default::get_codecs()
+    .make(&track.codec_params, &DecoderOptions::default())
+    .map_err(|e| format!("Unsupported codec: {e}"))?;
+
+  let mut out = WavOut::create(output, source_rate, target_rate)?;
+  let mut buffer: Option<SampleBuffer<f32>> = None;
+  let mut channels = 0;
+  loop {
+    let packet = match format.next_packet() {
+      Ok(packet) => packet,
+      Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+      Err(e) => return Err(format!("read: {e}")),
+    };
+    if packet.track_id() != track_id {
+      continue;
+    }
+    let decoded = match decoder.decode(&packet) {
+      Ok(decoded) => decoded,
+      // a corrupt packet costs a few milliseconds of audio, not the whole file
+      Err(SymphoniaError::DecodeError(e)) => {
+        eprintln!("Import: skipping undecodable packet: {}", e);
+        continue;
+      }
+      Err(e) => return Err(format!("decode: {e}")),
+    };
+
+    let signal = *decoded.spec();
+    channels = signal.channels.count();
+    let needed = decoded.capacity() * channels;
+    let buf = match buffer.as_mut() {
+      Some(buf) if buf.capacity() >= needed => buf,
+      _ => buffer.insert(SampleBuffer::new(decoded.capacity() as u64, signal)),
+    };
+    buf.copy_interleaved_ref(decoded);
+    out.push(buf.samples(), channels)?;
+  }
+
+  let sample_rate = out.sample_rate;
+  Ok(Imported {
+    source_rate,
+    channels,
+    sample_rate,
+    duration_ms: out.finish()?,
+  })
+}
+
+#[cfg(feature = "opus")]
+mod opus {
+  use audiopus::{coder::Decoder, Channels, SampleRate};
+  use ogg::reading::PacketReader;
+  use std::path::Path;
+
+  use super::{Imported, WavOut};
+
+  // the longest Opus packet, 120 ms at 48 kHz, per channel
+  const MAX_PACKET_FRAMES: usize = 5760;
+
+  /// Decode an Ogg/Opus file. Opus always decodes at 48 kHz; the rate in the
+  /// header is only what the encoder was fed.
+  pub fn decode_to_wav(input: &Path, output: &Path, target_rate: u32) -> Result<Imported, String> {
+    let file = std::fs::File::open(input).map_err(|e| format!("open: {e}"))?;
+    let mut reader = PacketReader::new(std::io::BufReader::new(file));
+    let mut next = || reader.read_packet().map_err(|e| format!("ogg read: {e}"));
+
+    let head = next()?.ok_or("Empty Ogg file")?;
+    if head.data.len() < 19 || &head.data[..8] != b"OpusHead" {
+      return Err("Not an Ogg/Opus file".into());
+    }
+    let channels = head.data[9] as usize;
+    let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as u64;
+    let source_rate = u32::from_le_bytes([head.data[12], head.data[13], head.data[14], head.data[15]]);
+    let layout = match (head.data[18], channels) {
+      (0, 1) => Channels::Mono,
+      (0, 2) => Channels::Stereo,
+      _ => return Err(format!("Opus files with {channels} channels are not supported")),
+    };
+    // OpusTags
+    next()?.ok_or("Ogg/Opus file has no audio")?;
+
+    let mut decoder = Decoder::new(SampleRate::Hz48000, layout).map_err(|e| format!("opus decoder: {e}"))?;
+    let mut out = WavOut::create(output, 48000, target_rate)?;
+    let mut pcm = vec![0f32; MAX_PACKET_FRAMES * channels];
+    // position in 48 kHz frames, counting the pre-skip the encoder added
+    let mut position = 0u64;
+    while let Some(packet) = next()? {
+      let frames = match decoder.decode_float(
+        Some((&packet.data[..]).try_into().map_err(|e| format!("opus packet: {e}"))?),
+        (&mut pcm[..]).try_into().map_err(|e| format!("opus buffer: {e}"))?,
+        false,
+      ) {
+        Ok(frames) => frames as u64,
+        // skipped for the same reason as in the symphonia path (super::decode_to_wav)
+        Err(e) => {
+          eprintln!("Import: skipping undecodable packet: {}", e);
+          continue;
+        }
+      };
+      let start = pre_skip.saturating_sub(position).min(frames);
+      // the last page's granule position marks where the audio ends
+      let end = if packet.last_in_stream() {
+        packet.absgp_page().saturating_sub(position).clamp(start, frames)
+      } else {
+        frames
+      };
+      out.push(&pcm[start as usize * channels..end as usize * channels], channels)?;
+      position += frames;
+    }
+
+    Ok(Imported {
+      source_rate,
+      channels,
+      sample_rate: out.sample_rate,
+      duration_ms: out.finish()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::{self, tone, TempDir};
+
+  #[test]
+  fn converts_24_bit_stereo_wav_to_mono_16k() {
+    let dir = TempDir::new("import-wav");
+    // one second of tone on the left channel only
+    let stereo: Vec<f32> = tone(48000, &[(0.5, 1000)]).into_iter().flat_map(|v| [v, 0.0]).collect();
+    let input = test_util::write_wav(dir.join("in.wav"), test_util::pcm_spec(2, 48000, 24), &stereo);
+    let output = dir.join("out.wav");
+
+    let imported = decode_to_wav(&input, &output, 16000).unwrap();
+    let stats = audio::analyze_wav(&output).unwrap();
+    let reader = hound::WavReader::open(&output).unwrap();
+
+    assert_eq!((imported.source_rate, imported.channels, imported.sample_rate), (48000, 2, 16000));
+    assert_eq!((reader.spec().channels, reader.spec().sample_rate, reader.spec().bits_per_sample), (1, 16000, 16));
+    assert_eq!(imported.duration_ms, 1000);
+    // averaged with the silent right channel: a 0.25 peak, about -12 dBFS
+    assert!((stats.peak_db + 12.0).abs() < 0.5, "peak {}", stats.peak_db);
+  }
+
+  #[cfg(feature = "opus")]
+  #[test]
+  fn decodes_ogg_opus() {
+    let dir = TempDir::new("import-opus");
+    // 1.5 s of tone at half scale
+    let wav = test_util::write_wav(dir.join("src.wav"), test_util::pcm_spec(1, 16000, 16), &tone(16000, &[(0.5, 1500)]));
+    let input = dir.join("in.opus");
+    let output = dir.join("out.wav");
+    let encoded = crate::encode::encode_for_upload(&wav, crate::encode::UploadFormat::Opus).unwrap();
+    std::fs::write(&input, &encoded.bytes).unwrap();
+
+    let imported = decode_to_wav(&input, &output, 16000).unwrap();
+    let stats = audio::analyze_wav(&output).unwrap();
+
+    assert_eq!((imported.source_rate, imported.channels, imported.sample_rate), (16000, 1, 16000));
+    // pre-skip and end trimming leave exactly the encoded length
+    assert!(imported.duration_ms.abs_diff(1500) <= 5, "{} ms", imported.duration_ms);
+    assert!((stats.peak_db + 6.0).abs() < 1.5, "peak {}", stats.peak_db);
+  }
+
+  #[test]
+  fn rejects_non_audio_files() {
+    let dir = TempDir::new("import-bad");
+    let input = dir.join("notes.txt");
+    std::fs::write(&input, b"not audio at all").unwrap();
+    assert!(decode_to_wav(&input, &dir.join("out.wav"), 16000).is_err());
+  }
+}
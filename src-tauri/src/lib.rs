@@ -1,35 +1,30 @@
 // src-tauri/src/lib.rs
 use base64::Engine;
-use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, path::PathBuf, sync::Arc, thread};
 use tauri::{Emitter, Manager, PhysicalPosition};
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::traits::DeviceTrait;
+
+use std::collections::HashMap;
 
 #[cfg(desktop)]
 use std::sync::OnceLock;
 
-// Thread-local recorder state (cpal::Stream is not Send/Sync)
-thread_local! {
-  static RECORDER_STATE: RefCell<Option<Recorder>> = RefCell::new(None);
-}
-
-struct Recorder {
-  path: PathBuf,
-  // dropping the stream stops capture
-  stream: cpal::Stream,
-  // closing tx stops writer thread
-  tx: crossbeam_channel::Sender<Vec<i16>>,
-  writer_join: thread::JoinHandle<anyhow::Result<()>>,
-  sample_rate: u32,
-}
-
-#[derive(Serialize)]
-struct RecordingStopped {
-  path: String,
-  sample_rate: u32,
-  duration_ms: u64,
-}
+mod audio;
+mod chunks;
+mod devices;
+mod encode;
+mod import;
+mod recorder;
+mod recovery;
+mod retention;
+mod segments;
+#[cfg(test)]
+mod test_util;
+
+use devices::{AudioHost, DeviceConfig, InputDevice};
+use recorder::{DeviceTest, RecorderService, RecorderStatus, RecordingStopped};
+use recovery::RecoveredRecording;
+use segments::SegmentPipeline;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -37,259 +32,143 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-fn start_recording(app: tauri::AppHandle) -> Result<String, String> {
-  let already_recording = RECORDER_STATE.with(|state| state.borrow().is_some());
-  if already_recording {
-    return Err("Already recording".into());
-  }
-
-  // choose an app cache dir for temp wav
-  let cache_dir = app
-    .path()
-    .app_cache_dir()
-    .map_err(|e| format!("cache dir: {e}"))?;
-  std::fs::create_dir_all(&cache_dir).map_err(|e| format!("mkdir: {e}"))?;
-  let path = cache_dir.join(format!("dictation-{}.wav", uuid::Uuid::new_v4()));
-
-  // Get settings to check for preferred input device
-  let settings = get_settings(app.clone())?;
-
-  let host = cpal::default_host();
-  let device = if settings.input_device_name.is_empty() {
-    // Use default device
-    host
-      .default_input_device()
-      .ok_or("No default input device (mic)".to_string())?
-  } else {
-    // Find device by name
-    match host
-      .input_devices()
-      .map_err(|e| format!("list devices: {e}"))?
-      .find(|d| {
-        d.name()
-          .map(|n| n == settings.input_device_name)
-          .unwrap_or(false)
-      }) {
-      Some(device) => device,
-      None => {
-        eprintln!(
-          "Warning: Input device '{}' not found. Falling back to default device.",
-          settings.input_device_name
-        );
-        // Fall back to default device
-        host
-          .default_input_device()
-          .ok_or("No default input device (mic)".to_string())?
-      }
-    }
-  };
-
-  let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
-  eprintln!("Start recording: using input device: {}", device_name);
-
-  let config = device
-    .default_input_config()
-    .map_err(|e| format!("default input config: {e}"))?;
-
-  let sample_rate = config.sample_rate().0;
-  let channels = config.channels() as usize;
-
-  eprintln!("Start recording: sample_rate: {}, channels: {}, format: {:?}", sample_rate, channels, config.sample_format());
-
-  let (tx, rx) = crossbeam_channel::unbounded::<Vec<i16>>();
-  let path_for_writer = path.clone();
-
-  // writer thread: write i16 PCM to WAV
-  let writer_join = thread::spawn(move || -> anyhow::Result<()> {
-    let spec = hound::WavSpec {
-      channels: 1,
-      sample_rate,
-      bits_per_sample: 16,
-      sample_format: hound::SampleFormat::Int,
-    };
-    let mut writer = hound::WavWriter::create(&path_for_writer, spec)?;
-    let mut total_samples = 0usize;
-    while let Ok(chunk) = rx.recv() {
-      total_samples += chunk.len();
-      for s in chunk {
-        writer.write_sample(s)?;
-      }
-    }
-    eprintln!("Writer thread: wrote {} total samples", total_samples);
-    writer.finalize()?;
-    Ok(())
-  });
-
-  // audio callback: convert to mono i16 and send to writer
-  let err_fn = |err| eprintln!("cpal stream error: {}", err);
-
-  let tx_cb = tx.clone();
-  let start_instant = std::time::Instant::now();
-  let duration_ms_shared = Arc::new(Mutex::new(0u64));
-  let duration_ms_cb = duration_ms_shared.clone();
-  let chunks_received = Arc::new(Mutex::new(0usize));
-  let chunks_received_cb = chunks_received.clone();
-
-  let stream = match config.sample_format() {
-    cpal::SampleFormat::F32 => device
-      .build_input_stream(
-        &config.into(),
-        move |data: &[f32], _| {
-          // update duration estimate
-          *duration_ms_cb.lock() = start_instant.elapsed().as_millis() as u64;
-
-          let mut mono = Vec::with_capacity(data.len() / channels);
-          for frame in data.chunks(channels) {
-            let v = frame[0].clamp(-1.0, 1.0);
-            mono.push((v * i16::MAX as f32) as i16);
-          }
-
-          let chunk_num = {
-            let mut count = chunks_received_cb.lock();
-            *count += 1;
-            *count
-          };
-
-          // Log first few chunks to verify audio is being captured
-          if chunk_num <= 3 {
-            let max_amp = mono.iter().map(|&s| s.abs()).max().unwrap_or(0);
-            eprintln!("Audio chunk #{}: {} samples, max amplitude: {}", chunk_num, mono.len(), max_amp);
-          }
-
-          let _ = tx_cb.send(mono);
-        },
-        err_fn,
-        None,
-      )
-      .map_err(|e| format!("build stream: {e}"))?,
-    cpal::SampleFormat::I16 => device
-      .build_input_stream(
-        &config.into(),
-        move |data: &[i16], _| {
-          *duration_ms_cb.lock() = start_instant.elapsed().as_millis() as u64;
-
-          let mut mono = Vec::with_capacity(data.len() / channels);
-          for frame in data.chunks(channels) {
-            mono.push(frame[0]);
-          }
-
-          let chunk_num = {
-            let mut count = chunks_received_cb.lock();
-            *count += 1;
-            *count
-          };
-
-          // Log first few chunks to verify audio is being captured
-          if chunk_num <= 3 {
-            let max_amp = mono.iter().map(|&s| s.abs()).max().unwrap_or(0);
-            eprintln!("Audio chunk #{}: {} samples, max amplitude: {}", chunk_num, mono.len(), max_amp);
-          }
+fn start_recording(recorder: tauri::State<'_, RecorderService>) -> Result<String, String> {
+  recorder.start()
+}
 
-          let _ = tx_cb.send(mono);
-        },
-        err_fn,
-        None,
-      )
-      .map_err(|e| format!("build stream: {e}"))?,
-    cpal::SampleFormat::U16 => device
-      .build_input_stream(
-        &config.into(),
-        move |data: &[u16], _| {
-          *duration_ms_cb.lock() = start_instant.elapsed().as_millis() as u64;
-
-          let mut mono = Vec::with_capacity(data.len() / channels);
-          for frame in data.chunks(channels) {
-            let v = frame[0] as i32 - 32768;
-            mono.push(v as i16);
-          }
+#[tauri::command]
+fn stop_recording(recorder: tauri::State<'_, RecorderService>) -> Result<RecordingStopped, String> {
+  recorder.stop()
+}
 
-          let chunk_num = {
-            let mut count = chunks_received_cb.lock();
-            *count += 1;
-            *count
-          };
+#[tauri::command]
+fn cancel_recording(recorder: tauri::State<'_, RecorderService>) -> Result<(), String> {
+  recorder.cancel()
+}
 
-          // Log first few chunks to verify audio is being captured
-          if chunk_num <= 3 {
-            let max_amp = mono.iter().map(|&s| s.abs()).max().unwrap_or(0);
-            eprintln!("Audio chunk #{}: {} samples, max amplitude: {}", chunk_num, mono.len(), max_amp);
-          }
+#[tauri::command]
+fn pause_recording(recorder: tauri::State<'_, RecorderService>) -> Result<(), String> {
+  recorder.pause()
+}
 
-          let _ = tx_cb.send(mono);
-        },
-        err_fn,
-        None,
-      )
-      .map_err(|e| format!("build stream: {e}"))?,
-    _ => return Err("Unsupported sample format".into()),
-  };
+#[tauri::command]
+fn resume_recording(recorder: tauri::State<'_, RecorderService>) -> Result<(), String> {
+  recorder.resume()
+}
 
-  stream.play().map_err(|e| format!("stream play: {e}"))?;
+/// Delete all cached recordings except one in progress. Returns how many were deleted.
+#[tauri::command]
+fn purge_recordings(app: tauri::AppHandle, recorder: tauri::State<'_, RecorderService>) -> Result<usize, String> {
+  let active = recorder.status()?.path.map(std::path::PathBuf::from);
+  let dir = retention::recordings_dir(&app)?;
+  let deleted = retention::purge(&dir, active.as_deref());
+  app
+    .state::<RecoveredRecordings>()
+    .0
+    .lock()
+    .retain(|r| std::path::Path::new(&r.path).exists());
+  eprintln!("Purged {} cached recordings", deleted);
+  Ok(deleted)
+}
 
-  let recorder = Recorder {
-    path: path.clone(),
-    stream,
-    tx,
-    writer_join,
-    sample_rate,
-  };
-  RECORDER_STATE.with(|state| {
-    *state.borrow_mut() = Some(recorder);
-  });
+/// Recordings repaired after a crash, waiting to be transcribed or discarded.
+/// Loaded at startup from the markers recovery leaves next to them.
+struct RecoveredRecordings(parking_lot::Mutex<Vec<RecoveredRecording>>);
 
-  Ok(path.to_string_lossy().to_string())
+#[tauri::command]
+fn list_recovered_recordings(recovered: tauri::State<'_, RecoveredRecordings>) -> Vec<RecoveredRecording> {
+  recovered.0.lock().clone()
 }
 
+/// Transcribe a recovered recording with the configured provider. It leaves
+/// the recovered list once transcribed.
 #[tauri::command]
-fn stop_recording() -> Result<RecordingStopped, String> {
-  let rec = RECORDER_STATE
-    .with(|state| state.borrow_mut().take())
-    .ok_or("Not recording".to_string())?;
-
-  let path = rec.path.clone();
-  eprintln!("Stop recording: stopping stream and writer for {}", path.to_string_lossy());
-
-  // stop capture by dropping stream, close writer by dropping tx
-  drop(rec.stream);
-  drop(rec.tx);
-
-  // wait writer finalize
-  rec
-    .writer_join
-    .join()
-    .map_err(|_| "writer thread panicked".to_string())?
-    .map_err(|e| format!("writer failed: {e}"))?;
-
-  // On Windows, wait a bit for file system to catch up
-  #[cfg(windows)]
-  {
-    std::thread::sleep(std::time::Duration::from_millis(200));
+async fn transcribe_recovered_recording(
+  app: tauri::AppHandle,
+  path: String,
+) -> Result<TranscribeResponse, TranscribeError> {
+  let known = app
+    .state::<RecoveredRecordings>()
+    .0
+    .lock()
+    .iter()
+    .any(|r| r.path == path);
+  if !known {
+    return Err("Not a recovered recording".into());
   }
+  let response = transcribe_with_settings(app.clone(), path.clone()).await?;
+  recovery::resolve(std::path::Path::new(&path));
+  app.state::<RecoveredRecordings>().0.lock().retain(|r| r.path != path);
+  Ok(response)
+}
 
-  // Verify file exists and has content
-  if !path.exists() {
-    return Err(format!("Recorded file does not exist: {}", path.to_string_lossy()));
+#[tauri::command]
+fn discard_recovered_recording(
+  recovered: tauri::State<'_, RecoveredRecordings>,
+  path: String,
+) -> Result<(), String> {
+  let mut list = recovered.0.lock();
+  if !list.iter().any(|r| r.path == path) {
+    return Err("Not a recovered recording".into());
   }
+  // may already be gone if it was transcribed with delete-after-transcription on
+  if std::path::Path::new(&path).exists() {
+    std::fs::remove_file(&path).map_err(|e| format!("remove recording: {e}"))?;
+  }
+  recovery::resolve(std::path::Path::new(&path));
+  list.retain(|r| r.path != path);
+  Ok(())
+}
 
-  let file_size = std::fs::metadata(&path)
-    .map_err(|e| format!("get file metadata: {e}"))?
-    .len();
+/// Transcribe an existing audio file (WAV, FLAC, MP3, Ogg Vorbis/Opus, M4A, ...)
+/// with the configured provider. It is first converted to the recorder's mono
+/// 16-bit WAV in the cache dir; the original file is never modified.
+#[tauri::command]
+async fn transcribe_file(app: tauri::AppHandle, path: String) -> Result<TranscribeResponse, TranscribeError> {
+  let settings = get_settings(app.clone())?;
+  let wav = retention::recordings_dir(&app)?.join(format!("import-{}.wav", uuid::Uuid::new_v4()));
+  eprintln!("Import: decoding {} to {}", path, wav.to_string_lossy());
 
-  eprintln!("Stop recording: file written, size: {} bytes", file_size);
+  let (input, output) = (std::path::PathBuf::from(&path), wav.clone());
+  let decoded = tauri::async_runtime::spawn_blocking(move || {
+    import::decode_to_wav(&input, &output, settings.target_sample_rate)
+  })
+  .await
+  .map_err(|e| format!("decode task: {e}"))?;
+  let imported = match decoded {
+    Ok(imported) => imported,
+    Err(e) => {
+      let _ = std::fs::remove_file(&wav);
+      return Err(e.into());
+    }
+  };
+  eprintln!(
+    "Import: {} ms, {} ch at {} Hz -> mono at {} Hz",
+    imported.duration_ms, imported.channels, imported.source_rate, imported.sample_rate
+  );
 
-  if file_size == 0 {
-    return Err("Recorded file is empty".into());
-  }
+  let result = transcribe_with_settings(app, wav.to_string_lossy().to_string()).await;
+  let _ = std::fs::remove_file(&wav);
+  result
+}
 
-  // duration: best-effort using file size/time is OK for MVP; keep simple:
-  // (you can store duration_ms in state if you want exact)
-  let duration_ms = 0;
+/// Text of a streamed recording, stitched from the segments transcribed in the
+/// background while it was being recorded. Waits for the ones still running.
+#[tauri::command]
+async fn finish_segment_transcription(
+  app: tauri::AppHandle,
+  path: String,
+) -> Result<TranscribeResponse, TranscribeError> {
+  let recording = std::path::Path::new(&path);
+  let response = app.state::<SegmentPipeline>().finish(recording).await?;
+  let settings = get_settings(app.clone())?;
+  retention::after_transcription(&app, &settings, recording);
+  Ok(response)
+}
 
-  Ok(RecordingStopped {
-    path: path.to_string_lossy().to_string(),
-    sample_rate: rec.sample_rate,
-    duration_ms,
-  })
+#[tauri::command]
+fn recording_status(recorder: tauri::State<'_, RecorderService>) -> Result<RecorderStatus, String> {
+  recorder.status()
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -299,7 +178,54 @@ struct AppSettings {
   openai_api_key: String,
   google_api_key: String,
   google_language: String,
-  input_device_name: String,
+  // cpal host to capture from ("ALSA", "JACK", ...); empty = platform default
+  audio_host: String,
+  // preferred microphones, most wanted first; each entry matches a device name
+  // exactly, as a substring or by its words. Empty = system default
+  input_devices: Vec<String>,
+  // capture format per "host/device name" (devices::config_key); devices not
+  // listed use their default config
+  input_configs: HashMap<String, DeviceConfig>,
+  // single device setting from older versions, moved into input_devices on load
+  #[serde(rename = "input_device_name", skip_serializing)]
+  legacy_input_device: String,
+  // None = downmix all channels, Some(i) = record only channel i (0-based)
+  input_channel: Option<u16>,
+  // rate the WAV is resampled to before upload; 0 keeps the device rate
+  target_sample_rate: u32,
+  // encoding sent to the provider: "wav", "flac" (lossless) or "opus" (needs the opus feature)
+  upload_format: String,
+  // also record what the system plays: "off", "mix" (one channel) or "separate" (mic left, system right)
+  system_audio: String,
+  // monitor/loopback source to record it from; empty = detect one
+  system_audio_device: String,
+  // transcribe in segments while recording, cut at pauses of segment_pause_ms or
+  // at segment_max_ms, so only the last segment is left when recording stops
+  streaming_transcription: bool,
+  segment_pause_ms: u32,
+  segment_max_ms: u32,
+  // hands-free: stop by itself after speech followed by vad_silence_ms of silence
+  vad_auto_stop: bool,
+  vad_silence_ms: u32,
+  vad_threshold_db: f32,
+  // keep the input stream open while idle and prepend the last preroll_ms to each recording
+  warm_mic: bool,
+  preroll_ms: u32,
+  // pre-flight: recordings shorter or quieter than this are not uploaded
+  min_recording_ms: u32,
+  min_speech_db: f32,
+  // "toggle": press to start, press again to stop; "push_to_talk": hold to record
+  hotkey_mode: String,
+  // push-to-talk releases sooner than this discard the recording
+  push_to_talk_min_hold_ms: u32,
+  // hotkey that discards the current recording, registered only while recording; empty = none
+  cancel_hotkey: String,
+  // hotkey that pauses and resumes, registered only while recording; empty = none
+  pause_hotkey: String,
+  // retention of cached dictation-*.wav files; 0 = no limit, the default, so deletion is opt-in
+  delete_after_transcription: bool,
+  keep_recordings: u32,
+  keep_recordings_days: u32,
   panel_visible: bool,
 }
 
@@ -310,18 +236,37 @@ impl Default for AppSettings {
       openai_api_key: String::new(),
       google_api_key: String::new(),
       google_language: "en-US".to_string(),
-      input_device_name: String::new(), // Empty means use default
+      audio_host: String::new(),
+      input_devices: Vec::new(), // Empty means use default
+      input_configs: HashMap::new(),
+      legacy_input_device: String::new(),
+      input_channel: None,
+      target_sample_rate: 16000, // what Whisper and Google resample to anyway
+      upload_format: "wav".to_string(),
+      system_audio: "off".to_string(),
+      system_audio_device: String::new(),
+      streaming_transcription: false,
+      segment_pause_ms: 700,
+      segment_max_ms: 30000, // well inside Google's one-minute limit
+      vad_auto_stop: false,
+      vad_silence_ms: 1500,
+      vad_threshold_db: -45.0,
+      warm_mic: false, // opt-in: the mic stays open while idle
+      preroll_ms: 500,
+      min_recording_ms: 300,
+      min_speech_db: -50.0, // loudest 20 ms frame, so clicks alone don't pass
+      hotkey_mode: "toggle".to_string(),
+      push_to_talk_min_hold_ms: 300,
+      cancel_hotkey: "Escape".to_string(),
+      pause_hotkey: "Ctrl+Shift+P".to_string(),
+      delete_after_transcription: false,
+      keep_recordings: 0,
+      keep_recordings_days: 0,
       panel_visible: true, // Default to visible
     }
   }
 }
 
-#[derive(Serialize)]
-struct InputDevice {
-  name: String,
-  is_default: bool,
-}
-
 #[tauri::command]
 fn show_panel(app: tauri::AppHandle) -> Result<(), String> {
   #[cfg(desktop)]
@@ -350,29 +295,70 @@ fn hide_panel(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn list_input_devices() -> Result<Vec<InputDevice>, String> {
-  let host = cpal::default_host();
-  let default_device = host.default_input_device();
-  let default_name = default_device
-    .as_ref()
-    .and_then(|d| d.name().ok())
-    .unwrap_or_default();
-
-  let devices: Result<Vec<_>, _> = host
-    .input_devices()
-    .map(|devices| {
-      devices
-        .filter_map(|device| {
-          device.name().ok().map(|name| InputDevice {
-            is_default: name == default_name,
-            name,
-          })
-        })
-        .collect()
-    })
-    .map_err(|e| format!("list devices: {e}"));
+fn list_audio_hosts() -> Vec<AudioHost> {
+  devices::list_hosts()
+}
+
+/// Upload formats this build can encode, as `upload_format` setting values.
+#[tauri::command]
+fn list_upload_formats() -> Vec<&'static str> {
+  encode::available_formats().into_iter().map(|f| f.setting()).collect()
+}
+
+/// Devices of `host`, or of the host saved in settings when not given.
+#[tauri::command]
+fn list_input_devices(app: tauri::AppHandle, host: Option<String>) -> Result<Vec<InputDevice>, String> {
+  let host = match host {
+    Some(host) => host,
+    None => get_settings(app)?.audio_host,
+  };
+  devices::list_input_devices(&devices::host(&host))
+}
+
+/// Record `duration_ms` (default 3 s) from `device_name` (empty = system default)
+/// and report levels, clipping and a playable WAV. Runs beside the recorder,
+/// so it works without a dictation and costs no API calls.
+#[tauri::command]
+async fn test_input_device(
+  app: tauri::AppHandle,
+  device_name: String,
+  duration_ms: Option<u32>,
+) -> Result<DeviceTest, String> {
+  let mut settings = get_settings(app)?;
+  // an exact name always beats fuzzy matches of other devices
+  settings.input_devices = vec![device_name];
+  let duration_ms = duration_ms.unwrap_or(3000).clamp(500, 10_000);
+  // the stream is not Send, so capture on a blocking worker thread
+  tauri::async_runtime::spawn_blocking(move || recorder::test_capture(&settings, duration_ms))
+    .await
+    .map_err(|e| format!("device test: {e}"))?
+}
 
-  devices
+/// Remember `config` as the capture format of `device_name` on `host`
+/// (empty name = the default device, None = the device's default config).
+/// Fails if the device can't do it. Which host is recorded from is left to
+/// the `audio_host` setting.
+#[tauri::command]
+fn select_input_config(
+  app: tauri::AppHandle,
+  host: String,
+  device_name: String,
+  config: Option<DeviceConfig>,
+) -> Result<(), String> {
+  let host = devices::host(&host);
+  let device = devices::find_device(&host, &device_name)?;
+  let device_name = device.name().map_err(|e| format!("device name: {e}"))?;
+  if let Some(config) = &config {
+    devices::find_config(&device, config)?;
+  }
+
+  let key = devices::config_key(host.id().name(), &device_name);
+  let mut settings = get_settings(app.clone())?;
+  match config {
+    Some(config) => settings.input_configs.insert(key, config),
+    None => settings.input_configs.remove(&key),
+  };
+  save_settings(app, settings)
 }
 
 #[derive(Serialize)]
@@ -380,26 +366,121 @@ struct TranscribeResponse {
   text: String,
 }
 
+/// Error returned by the transcribe commands. `NothingHeard` means the
+/// recording was rejected before upload or the provider found no speech in
+/// it, so the UI can say so instead of showing a failure.
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+enum TranscribeError {
+  NothingHeard(String),
+  Failed(String),
+}
+
+impl From<String> for TranscribeError {
+  fn from(e: String) -> Self {
+    TranscribeError::Failed(e)
+  }
+}
+
+impl From<&str> for TranscribeError {
+  fn from(e: &str) -> Self {
+    TranscribeError::Failed(e.to_string())
+  }
+}
+
+/// Transcribe `audio_path` with the provider, key and language saved in settings.
+async fn transcribe_with_settings(
+  app: tauri::AppHandle,
+  audio_path: String,
+) -> Result<TranscribeResponse, TranscribeError> {
+  let settings = get_settings(app.clone())?;
+  if settings.provider == "google" {
+    let api_key = settings.google_api_key.trim().to_string();
+    if api_key.is_empty() {
+      return Err("Google API key is not set".into());
+    }
+    let language = Some(settings.google_language).filter(|l| !l.trim().is_empty());
+    google_transcribe(app, audio_path, api_key, language, None, None).await
+  } else {
+    let api_key = settings.openai_api_key.trim().to_string();
+    if api_key.is_empty() {
+      return Err("OpenAI API key is not set".into());
+    }
+    openai_transcribe(app, audio_path, api_key, None, None, None).await
+  }
+}
+
+/// Shared by every provider: reject recordings that are too short or too quiet
+/// to contain speech. Whisper in particular invents text ("Thank you.") for silence.
+/// Reads the whole recording, so it runs off the async runtime.
+async fn preflight_check(app: &tauri::AppHandle, path: &std::path::Path) -> Result<(), TranscribeError> {
+  let settings = get_settings(app.clone())?;
+  let wav = path.to_path_buf();
+  let stats = tauri::async_runtime::spawn_blocking(move || audio::analyze_wav(&wav))
+    .await
+    .map_err(|e| format!("analyze task: {e}"))??;
+  eprintln!(
+    "Pre-flight: {} ms, peak {:.1} dBFS, loudest frame {:.1} dBFS, {:.0}% silence",
+    stats.duration_ms,
+    stats.peak_db,
+    stats.loudest_frame_db,
+    stats.silence_ratio * 100.0
+  );
+
+  if stats.duration_ms < settings.min_recording_ms as u64 {
+    return Err(TranscribeError::NothingHeard(format!(
+      "Recording too short ({} ms)",
+      stats.duration_ms
+    )));
+  }
+  if stats.loudest_frame_db < settings.min_speech_db {
+    return Err(TranscribeError::NothingHeard(format!(
+      "Recording too quiet ({:.0} dBFS)",
+      stats.loudest_frame_db
+    )));
+  }
+  Ok(())
+}
+
+/// Encode a recording in the configured upload format, or as WAV when
+/// `provider` doesn't accept it. Runs off the async runtime; FLAC of a long
+/// recording takes a moment.
+async fn encode_upload(
+  app: &tauri::AppHandle,
+  provider: &'static str,
+  path: &std::path::Path,
+) -> Result<encode::Encoded, String> {
+  let settings = get_settings(app.clone())?;
+  let format = encode::upload_format(provider, &settings.upload_format)?;
+  if format != encode::UploadFormat::from_setting(&settings.upload_format) {
+    eprintln!(
+      "Upload: {} is not accepted by {}, sending {:?}",
+      settings.upload_format, provider, format
+    );
+  }
+  let path = path.to_path_buf();
+  tauri::async_runtime::spawn_blocking(move || encode::encode_for_upload(&path, format))
+    .await
+    .map_err(|e| format!("encode task: {e}"))?
+}
+
 #[tauri::command]
 async fn openai_transcribe(
+  app: tauri::AppHandle,
   audio_path: String,
   api_key: String,
   model: Option<String>,
   language: Option<String>,
   prompt: Option<String>,
-) -> Result<TranscribeResponse, String> {
+) -> Result<TranscribeResponse, TranscribeError> {
   let model = model.unwrap_or_else(|| "whisper-1".to_string());
 
-  // OpenAI Speech-to-Text: POST /v1/audio/transcriptions (multipart file + model)
-  // supports wav/webm/mp3/m4a etc.
-  let url = "https://api.openai.com/v1/audio/transcriptions";
-
   eprintln!("OpenAI transcribe: reading file from {}", audio_path);
 
   // On Windows, ensure file is ready by checking existence and size
   let path = std::path::Path::new(&audio_path);
   if !path.exists() {
-    return Err(format!("Audio file does not exist: {}", audio_path));
+    return Err(format!("Audio file does not exist: {}", audio_path).into());
   }
 
   // Small delay on Windows to ensure file is fully flushed
@@ -408,19 +489,45 @@ async fn openai_transcribe(
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
   }
 
-  let file_bytes = tokio::fs::read(&audio_path)
-    .await
-    .map_err(|e| format!("read audio: {e}"))?;
+  preflight_check(&app, path).await?;
+
+  // over 25 MB it goes up in chunks
+  let text = chunks::transcribe(&app, "openai", path, |chunk| {
+    openai_request(app.clone(), chunk, api_key.clone(), model.clone(), language.clone(), prompt.clone())
+  })
+  .await?;
+
+  if let Ok(settings) = get_settings(app.clone()) {
+    retention::after_transcription(&app, &settings, path);
+  }
+
+  Ok(TranscribeResponse { text })
+}
+
+/// One upload to OpenAI; `path` fits its size limit.
+async fn openai_request(
+  app: tauri::AppHandle,
+  path: std::path::PathBuf,
+  api_key: String,
+  model: String,
+  language: Option<String>,
+  prompt: Option<String>,
+) -> Result<String, TranscribeError> {
+  // OpenAI Speech-to-Text: POST /v1/audio/transcriptions (multipart file + model)
+  // supports wav/webm/mp3/m4a etc.
+  let url = "https://api.openai.com/v1/audio/transcriptions";
+
+  let encoded = encode_upload(&app, "openai", &path).await?;
 
-  eprintln!("OpenAI transcribe: read {} bytes from file", file_bytes.len());
+  eprintln!("OpenAI transcribe: {} bytes as {:?}", encoded.bytes.len(), encoded.format);
 
-  if file_bytes.is_empty() {
+  if encoded.bytes.is_empty() {
     return Err("Audio file is empty".into());
   }
 
-  let file_part = reqwest::multipart::Part::bytes(file_bytes)
-    .file_name("audio.wav")
-    .mime_str("audio/wav")
+  let file_part = reqwest::multipart::Part::bytes(encoded.bytes)
+    .file_name(encoded.format.file_name())
+    .mime_str(encoded.format.mime())
     .map_err(|e| format!("mime: {e}"))?;
 
   let mut form = reqwest::multipart::Form::new()
@@ -451,7 +558,7 @@ async fn openai_transcribe(
   if !status.is_success() {
     let body = resp.text().await.unwrap_or_default();
     eprintln!("OpenAI transcribe: error response body: {}", body);
-    return Err(format!("OpenAI error {status}: {body}"));
+    return Err(format!("OpenAI error {status}: {body}").into());
   }
 
   let v: serde_json::Value = resp.json().await.map_err(|e| format!("json: {e}"))?;
@@ -465,23 +572,24 @@ async fn openai_transcribe(
 
   eprintln!("OpenAI transcribe: extracted text: '{}'", text);
 
-  Ok(TranscribeResponse { text })
+  Ok(text)
 }
 
 #[tauri::command]
 async fn google_transcribe(
+  app: tauri::AppHandle,
   audio_path: String,
   api_key: String,
   language: Option<String>,
   model: Option<String>,
   enable_automatic_punctuation: Option<bool>,
-) -> Result<TranscribeResponse, String> {
+) -> Result<TranscribeResponse, TranscribeError> {
   eprintln!("Google transcribe: reading file from {}", audio_path);
 
   // On Windows, ensure file is ready by checking existence
   let path = std::path::Path::new(&audio_path);
   if !path.exists() {
-    return Err(format!("Audio file does not exist: {}", audio_path));
+    return Err(format!("Audio file does not exist: {}", audio_path).into());
   }
 
   // Small delay on Windows to ensure file is fully flushed
@@ -490,15 +598,7 @@ async fn google_transcribe(
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
   }
 
-  let file_bytes = tokio::fs::read(&audio_path)
-    .await
-    .map_err(|e| format!("read audio: {e}"))?;
-
-  eprintln!("Google transcribe: read {} bytes from file", file_bytes.len());
-
-  if file_bytes.is_empty() {
-    return Err("Audio file is empty".into());
-  }
+  preflight_check(&app, path).await?;
 
   let wav_reader =
     hound::WavReader::open(&audio_path).map_err(|e| format!("wav open: {e}"))?;
@@ -509,41 +609,60 @@ async fn google_transcribe(
     return Err("Google Speech-to-Text requires 16-bit LINEAR16 audio".into());
   }
 
-  // Check if audio contains actual sound (not just silence)
-  let samples: Vec<i16> = wav_reader.into_samples::<i16>()
-    .filter_map(|s| s.ok())
-    .collect();
+  let language_code = language.unwrap_or_else(|| "en-US".to_string());
+  let model = model.unwrap_or_else(|| "default".to_string());
+  let enable_automatic_punctuation = enable_automatic_punctuation.unwrap_or(true);
 
-  if samples.is_empty() {
-    return Err("Audio file contains no samples".into());
+  // speech:recognize takes about a minute; longer recordings go up in chunks
+  let text = chunks::transcribe(&app, "google", path, |chunk| {
+    google_request(
+      app.clone(),
+      chunk,
+      api_key.clone(),
+      language_code.clone(),
+      model.clone(),
+      enable_automatic_punctuation,
+    )
+  })
+  .await?;
+
+  if let Ok(settings) = get_settings(app.clone()) {
+    retention::after_transcription(&app, &settings, path);
   }
 
-  // Check if audio is mostly silent (all samples near zero)
-  let max_amplitude = samples.iter()
-    .map(|&s| s.abs() as u32)
-    .max()
-    .unwrap_or(0);
+  Ok(TranscribeResponse { text })
+}
+
+/// One synchronous recognize request; `path` fits Google's limits.
+async fn google_request(
+  app: tauri::AppHandle,
+  path: std::path::PathBuf,
+  api_key: String,
+  language_code: String,
+  model: String,
+  enable_automatic_punctuation: bool,
+) -> Result<String, TranscribeError> {
+  let encoded = encode_upload(&app, "google", &path).await?;
 
-  eprintln!("Google transcribe: audio samples: {}, max amplitude: {}", samples.len(), max_amplitude);
+  eprintln!("Google transcribe: {} bytes as {:?}", encoded.bytes.len(), encoded.format);
 
-  // If max amplitude is very low, the audio is likely silent
-  if max_amplitude < 100 {
-    eprintln!("Google transcribe: WARNING - audio appears to be silent or very quiet (max amplitude: {})", max_amplitude);
+  if encoded.bytes.is_empty() {
+    return Err("Audio file is empty".into());
   }
 
-  let encoded_audio = base64::engine::general_purpose::STANDARD.encode(file_bytes);
-  let language_code = language.unwrap_or_else(|| "en-US".to_string());
-  let model = model.unwrap_or_else(|| "default".to_string());
-  let enable_automatic_punctuation = enable_automatic_punctuation.unwrap_or(true);
+  let encoded_audio = base64::engine::general_purpose::STANDARD.encode(&encoded.bytes);
 
   let body = serde_json::json!({
     "audio": { "content": encoded_audio },
     "config": {
       "enableAutomaticPunctuation": enable_automatic_punctuation,
-      "encoding": "LINEAR16",
+      "encoding": encoded.format.google_encoding(),
       "languageCode": language_code,
       "model": model,
-      "sampleRateHertz": spec.sample_rate
+      "sampleRateHertz": encoded.sample_rate,
+      // mic + system audio recordings are stereo; without this only channel 1 is recognized
+      "audioChannelCount": encoded.channels,
+      "enableSeparateRecognitionPerChannel": encoded.channels > 1
     }
   });
 
@@ -565,7 +684,7 @@ async fn google_transcribe(
   if !status.is_success() {
     let body = resp.text().await.unwrap_or_default();
     eprintln!("Google transcribe: error response body: {}", body);
-    return Err(format!("Google Speech error {status}: {body}"));
+    return Err(format!("Google Speech error {status}: {body}").into());
   }
 
   let v: serde_json::Value = resp.json().await.map_err(|e| format!("json: {e}"))?;
@@ -576,29 +695,45 @@ async fn google_transcribe(
     if let Some(results_array) = results.as_array() {
       if results_array.is_empty() {
         eprintln!("Google transcribe: results array is empty - no speech detected");
-        return Err("No speech detected in audio. The audio may be silent or too quiet.".into());
+        return Err(TranscribeError::NothingHeard(
+          "No speech detected in audio. The audio may be silent or too quiet.".into(),
+        ));
+      }
+      let transcripts: Vec<&str> = results_array
+        .iter()
+        .filter_map(|r| {
+          r.get("alternatives")
+            .and_then(|a| a.as_array())
+            .and_then(|a| a.first())
+            .and_then(|a| a.get("transcript"))
+            .and_then(|t| t.as_str())
+        })
+        .collect();
+      if encoded.channels > 1 {
+        // one result per channel and segment
+        transcripts
+          .iter()
+          .map(|t| t.trim())
+          .filter(|t| !t.is_empty())
+          .collect::<Vec<_>>()
+          .join(" ")
+      } else {
+        transcripts.first().copied().unwrap_or("").to_string()
       }
-      results_array
-        .get(0)
-        .and_then(|r| r.get("alternatives"))
-        .and_then(|a| a.as_array())
-        .and_then(|a| a.get(0))
-        .and_then(|a| a.get("transcript"))
-        .and_then(|t| t.as_str())
-        .unwrap_or("")
-        .to_string()
     } else {
       eprintln!("Google transcribe: results is not an array");
       return Err("Invalid response format: results is not an array".into());
     }
   } else {
     eprintln!("Google transcribe: no 'results' field in response - no speech detected");
-    return Err("No speech detected in audio. The audio may be silent, too quiet, or the language may not match.".into());
+    return Err(TranscribeError::NothingHeard(
+      "No speech detected in audio. The audio may be silent, too quiet, or the language may not match.".into(),
+    ));
   };
 
   eprintln!("Google transcribe: extracted text: '{}'", text);
 
-  Ok(TranscribeResponse { text })
+  Ok(text)
 }
 
 #[tauri::command]
@@ -654,9 +789,13 @@ fn get_settings(app: tauri::AppHandle) -> Result<AppSettings, String> {
   let content = std::fs::read_to_string(&settings_path)
     .map_err(|e| format!("read settings: {e}"))?;
 
-  let settings: AppSettings = serde_json::from_str(&content)
+  let mut settings: AppSettings = serde_json::from_str(&content)
     .map_err(|e| format!("parse settings: {e}"))?;
 
+  if settings.input_devices.is_empty() && !settings.legacy_input_device.is_empty() {
+    settings.input_devices = vec![std::mem::take(&mut settings.legacy_input_device)];
+  }
+
   Ok(settings)
 }
 
@@ -675,6 +814,11 @@ fn save_settings(app: tauri::AppHandle, settings: AppSettings) -> Result<(), Str
   std::fs::write(&settings_path, content)
     .map_err(|e| format!("write settings: {e}"))?;
 
+  // Warm mic / input device changes apply to the idle stream right away
+  if let Some(recorder) = app.try_state::<RecorderService>() {
+    recorder.apply_settings();
+  }
+
   // Apply panel visibility setting
   #[cfg(desktop)]
   {
@@ -690,6 +834,116 @@ fn save_settings(app: tauri::AppHandle, settings: AppSettings) -> Result<(), Str
   Ok(())
 }
 
+#[cfg(desktop)]
+fn push_to_talk_enabled(app: &tauri::AppHandle) -> bool {
+  get_settings(app.clone())
+    .map(|s| s.hotkey_mode == "push_to_talk")
+    .unwrap_or(false)
+}
+
+/// When the push-to-talk hotkey went down; None while it is up.
+#[cfg(desktop)]
+static PUSH_TO_TALK_PRESSED_AT: parking_lot::Mutex<Option<std::time::Instant>> =
+  parking_lot::Mutex::new(None);
+
+/// Push-to-talk: pressing starts a recording, releasing stops it. Releases
+/// before the minimum hold discard the recording instead, so brushing the
+/// hotkey doesn't transcribe and paste a blip. The panel does the actual work,
+/// as it does for the toggle event.
+#[cfg(desktop)]
+fn push_to_talk(app: &tauri::AppHandle, state: tauri_plugin_global_shortcut::ShortcutState) {
+  use tauri_plugin_global_shortcut::ShortcutState;
+
+  let mut pressed_at = PUSH_TO_TALK_PRESSED_AT.lock();
+  let event = match state {
+    // key repeat sends more presses while held; only the first one counts
+    ShortcutState::Pressed if pressed_at.is_none() => {
+      *pressed_at = Some(std::time::Instant::now());
+      "dictation-start"
+    }
+    ShortcutState::Pressed => return,
+    ShortcutState::Released => {
+      let Some(at) = pressed_at.take() else { return };
+      let min_hold_ms = get_settings(app.clone())
+        .map(|s| s.push_to_talk_min_hold_ms)
+        .unwrap_or(0);
+      let held = at.elapsed();
+      if held.as_millis() < min_hold_ms as u128 {
+        eprintln!("Push-to-talk: released after {} ms, ignoring tap", held.as_millis());
+        "dictation-discard"
+      } else {
+        "dictation-stop"
+      }
+    }
+  };
+  if let Err(e) = app.emit_to("panel", event, ()) {
+    eprintln!("✗ Failed to emit '{}': {:?}", event, e);
+  }
+}
+
+/// Recording-only shortcuts currently registered, so they can be removed even
+/// if the pause or cancel hotkey setting changed mid-recording.
+#[cfg(desktop)]
+static RECORDING_SHORTCUTS: parking_lot::Mutex<Vec<tauri_plugin_global_shortcut::Shortcut>> =
+  parking_lot::Mutex::new(Vec::new());
+
+/// Register the hotkeys that only make sense during a recording (the
+/// configurable pause and cancel keys), or remove them again, so they don't shadow other
+/// apps' shortcuts the rest of the time. Safe to call from any thread:
+/// registration is queued onto the main thread, which may itself be blocked
+/// waiting on the recorder.
+fn set_recording_shortcuts(app: &tauri::AppHandle, recording: bool) {
+  #[cfg(desktop)]
+  {
+    use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+    let (pause_hotkey, cancel_hotkey) = get_settings(app.clone())
+      .map(|s| (s.pause_hotkey, s.cancel_hotkey))
+      .unwrap_or_default();
+    let handle = app.clone();
+    let queued = app.run_on_main_thread(move || {
+      let shortcuts = handle.global_shortcut();
+      let mut registered = RECORDING_SHORTCUTS.lock();
+
+      for shortcut in registered.drain(..) {
+        if let Err(e) = shortcuts.unregister(shortcut) {
+          eprintln!("Failed to unregister recording shortcut {:?}: {:?}", shortcut, e);
+        }
+      }
+      if !recording {
+        return;
+      }
+
+      let toggle_pause: fn(&RecorderService) = RecorderService::toggle_pause;
+      let actions = [
+        ("pause", &pause_hotkey, toggle_pause),
+        ("cancel", &cancel_hotkey, RecorderService::cancel_nowait),
+      ];
+      for (name, hotkey, action) in actions {
+        if hotkey.trim().is_empty() {
+          continue;
+        }
+        match hotkey.parse::<Shortcut>() {
+          Ok(shortcut) => match shortcuts.on_shortcut(shortcut, move |app, _, event| {
+            if event.state() == ShortcutState::Pressed {
+              action(&app.state::<RecorderService>());
+            }
+          }) {
+            Ok(_) => registered.push(shortcut),
+            Err(e) => eprintln!("Failed to register {} shortcut {}: {:?}", name, hotkey, e),
+          },
+          Err(e) => eprintln!("Invalid {} hotkey '{}': {:?}", name, hotkey, e),
+        }
+      }
+    });
+    if let Err(e) = queued {
+      eprintln!("Failed to queue recording shortcuts: {:?}", e);
+    }
+  }
+  #[cfg(not(desktop))]
+  let _ = (app, recording);
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
@@ -708,7 +962,9 @@ pub fn run() {
             .with_handler(|app, shortcut, event| {
               eprintln!("Global shortcut triggered: {:?}, state: {:?}", shortcut, event.state());
               if let Some(expected) = EXPECTED_SHORTCUT.get() {
-                if shortcut == expected && event.state() == ShortcutState::Pressed {
+                if shortcut == expected && push_to_talk_enabled(app) {
+                  push_to_talk(app, event.state());
+                } else if shortcut == expected && event.state() == ShortcutState::Pressed {
                   eprintln!("✓ Matching shortcut detected, emitting toggle event");
                   // Use app.emit to send to all windows, or window.emit for specific window
                   if let Some(w) = app.get_webview_window("panel") {
@@ -733,6 +989,25 @@ pub fn run() {
       },
     )
     .setup(|app| {
+      // ---------- Crash recovery, then retention sweep (before anything records) ----------
+      // recovery goes first so the sweep can't delete a recording before it is offered
+      let mut recovered = Vec::new();
+      match (get_settings(app.handle().clone()), retention::recordings_dir(app.handle())) {
+        (Ok(settings), Ok(dir)) => {
+          let files = || retention::list_recordings(&dir).into_iter().map(|(path, _)| path);
+          recovery::recover(files());
+          recovered = recovery::pending(&dir, files());
+          retention::sweep(&dir, &settings);
+          retention::remove_stale_segments(&dir);
+        }
+        (Err(e), _) | (_, Err(e)) => eprintln!("Retention: startup sweep skipped: {}", e),
+      }
+      app.manage(RecoveredRecordings(parking_lot::Mutex::new(recovered)));
+
+      // ---------- Recorder service (owns the audio thread) ----------
+      app.manage(SegmentPipeline::default());
+      app.manage(RecorderService::spawn(app.handle().clone()));
+
       // ---------- Tray ----------
       #[cfg(desktop)]
       {
@@ -834,7 +1109,7 @@ pub fn run() {
         use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut};
 
         let hk = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyT);
-        match app.handle().global_shortcut().register(hk.clone()) {
+        match app.handle().global_shortcut().register(hk) {
           Ok(_) => {
             eprintln!("✓ Global shortcut Ctrl+Shift+T registered successfully");
           }
@@ -875,12 +1150,26 @@ pub fn run() {
       greet,
       start_recording,
       stop_recording,
+      cancel_recording,
+      pause_recording,
+      resume_recording,
+      recording_status,
+      finish_segment_transcription,
+      transcribe_file,
+      purge_recordings,
+      list_recovered_recordings,
+      transcribe_recovered_recording,
+      discard_recovered_recording,
       openai_transcribe,
       google_transcribe,
       paste_text,
       get_settings,
       save_settings,
+      list_audio_hosts,
+      list_upload_formats,
       list_input_devices,
+      select_input_config,
+      test_input_device,
       show_panel,
       hide_panel
     ])
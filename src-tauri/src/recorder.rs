@@ -0,0 +1,1358 @@
+// src-tauri/src/recorder.rs
+//
+// Recorder service: a dedicated audio thread owns the (non-Send) cpal::Stream
+// and is driven over a command channel. The service itself is registered as
+// Tauri managed state, so commands, tray handlers and shortcut handlers can
+// all start/stop the same recording regardless of which thread they run on.
+//
+// The cpal callback runs on the device's real-time thread: it only downmixes
+// into a preallocated lock-free ring and bumps atomic counters. Everything
+// else (pre-roll, resampling, metering, file I/O) happens on the reader side.
+use cpal::traits::{DeviceTrait, StreamTrait};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use serde::Serialize;
+use std::{
+  path::PathBuf,
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+  },
+  thread,
+  time::{Duration, Instant},
+};
+use tauri::{Emitter, Manager};
+
+use crate::audio::{self, ChannelSelect};
+use crate::devices;
+use crate::retention;
+use crate::segments::{self, SegmentPipeline};
+use crate::{get_settings, AppSettings};
+
+/// Seconds of mono audio each capture's ring holds. If the reader falls this
+/// far behind, the callback drops the newest samples and counts an overrun.
+const RING_SECONDS: usize = 4;
+/// How often the writer drains the rings while recording.
+const WRITER_POLL: Duration = Duration::from_millis(10);
+/// How often the recorder thread moves a warm stream's ring into its pre-roll.
+const WARM_POLL: Duration = Duration::from_millis(100);
+/// How often an idle warm stream checks it is still on the preferred device.
+/// Enumerating devices is slow, so it runs on a helper thread, never on the
+/// recorder thread where it would hold up a start.
+const WARM_RECHECK: Duration = Duration::from_secs(5);
+/// Shortest streamed segment; pauses before this don't start a new one.
+const SEGMENT_MIN_MS: u32 = 4000;
+
+/// What the writer produced.
+struct Written {
+  stats: audio::RecordingStats,
+  // segment files handed to the background transcription pipeline
+  segments: usize,
+}
+
+/// What the writer thread hands back: the mic ring's reader, so the stream
+/// can stay warm, and the outcome of writing the file.
+type WriterOutput = (rtrb::Consumer<f32>, anyhow::Result<Written>);
+
+struct Recorder {
+  path: PathBuf,
+  capture: Capture,
+  // monitor/loopback capture recorded alongside the mic, when enabled
+  system: Option<Capture>,
+  writer_join: thread::JoinHandle<WriterOutput>,
+  // closing it tells the writer to drain what is left and finalize; sending
+  // `true` first means the recording is being discarded
+  stop_tx: Sender<bool>,
+  // capture counters when the recording started, to report this recording's share
+  overruns_at_start: u64,
+  dropped_at_start: u64,
+  sample_rate: u32,
+  started_at: Instant,
+  // pause bookkeeping for elapsed time; the samples themselves are dropped in the callback
+  paused_at: Option<Instant>,
+  paused_total: Duration,
+}
+
+impl Recorder {
+  /// Time spent actively recording, excluding pauses.
+  fn active_elapsed(&self) -> Duration {
+    let paused_now = self.paused_at.map(|t| t.elapsed()).unwrap_or_default();
+    self
+      .started_at
+      .elapsed()
+      .saturating_sub(self.paused_total + paused_now)
+  }
+}
+
+/// An open input stream. Its callback fills a ring that the active
+/// recording's writer drains, or, while the mic is kept warm between
+/// recordings, the recorder thread moves into the pre-roll.
+struct Capture {
+  // dropping the stream stops capture
+  _stream: cpal::Stream,
+  // matches stream errors to the capture they came from
+  id: u64,
+  shared: Arc<CaptureShared>,
+  // read side of the callback's ring; lent to the writer while recording
+  consumer: Option<rtrb::Consumer<f32>>,
+  // most recent audio while idle, prepended to the next recording
+  preroll: audio::PrerollBuffer,
+  device_rate: u32,
+  // which device was opened and why
+  choice: devices::DeviceChoice,
+}
+
+impl Capture {
+  /// Move what the callback has produced since the last call into the pre-roll.
+  fn drain_to_preroll(&mut self) {
+    if let Some(consumer) = self.consumer.as_mut() {
+      let preroll = &mut self.preroll;
+      let _ = drain_ring(consumer, |samples| -> Result<(), ()> {
+        preroll.push(samples);
+        Ok(())
+      });
+    }
+  }
+}
+
+static NEXT_CAPTURE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Counters shared with the callback; atomics only, the callback never blocks.
+struct CaptureShared {
+  // recording is paused: the callback drops samples instead of queuing them
+  paused: AtomicBool,
+  callbacks: AtomicU64,
+  // callbacks that found the ring full, and how many samples they dropped
+  overruns: AtomicU64,
+  dropped_samples: AtomicU64,
+}
+
+/// Hand everything currently in the ring to `f` (as at most two slices,
+/// oldest first) and release the space to the callback.
+fn drain_ring<E>(
+  consumer: &mut rtrb::Consumer<f32>,
+  mut f: impl FnMut(&[f32]) -> Result<(), E>,
+) -> Result<usize, E> {
+  let available = consumer.slots();
+  let Ok(chunk) = consumer.read_chunk(available) else {
+    return Ok(0);
+  };
+  let (first, second) = chunk.as_slices();
+  if !first.is_empty() {
+    f(first)?;
+  }
+  if !second.is_empty() {
+    f(second)?;
+  }
+  chunk.commit_all();
+  Ok(available)
+}
+
+#[derive(Serialize, Clone)]
+pub struct RecordingStopped {
+  pub path: String,
+  pub sample_rate: u32,
+  // duration_ms, level and clipping stats of the written audio
+  #[serde(flatten)]
+  pub stats: audio::RecordingStats,
+  // times the writer fell behind and the callback had to drop audio, and how much
+  pub overruns: u64,
+  pub dropped_ms: u64,
+  // segments already transcribed in the background (finish_segment_transcription
+  // stitches them); 0 = transcribe `path` as a whole
+  pub segments: usize,
+}
+
+/// Payload of the `recording-error` event. `recording` is the finalized partial
+/// file when capture failed mid-recording, so it can still be transcribed.
+/// Errors that are not `fatal` leave the recording running; the panel only warns.
+#[derive(Serialize, Clone)]
+pub struct RecordingError {
+  pub message: String,
+  pub recording: Option<RecordingStopped>,
+  pub fatal: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RecorderStatus {
+  pub recording: bool,
+  pub path: Option<String>,
+  pub sample_rate: Option<u32>,
+  // active time only; paused stretches are not counted
+  pub elapsed_ms: u64,
+  pub paused: bool,
+  // input stream is held open between recordings for pre-roll
+  pub warm_mic: bool,
+}
+
+enum RecorderCommand {
+  Start { reply: Sender<Result<String, String>> },
+  Stop { reply: Sender<Result<RecordingStopped, String>> },
+  // reply is None when sent from the cancel hotkey, which must not block
+  Cancel { reply: Option<Sender<Result<(), String>>> },
+  Status { reply: Sender<RecorderStatus> },
+  Pause { reply: Sender<Result<(), String>> },
+  Resume { reply: Sender<Result<(), String>> },
+  // from the pause hotkey, which must not block waiting for a reply
+  TogglePause,
+  // settings were saved: open, reopen or close the warm-mic stream
+  ApplySettings,
+  // sent by the writer thread when voice activity detection hears the end of speech
+  AutoStop { path: PathBuf },
+  // the device the preferences pick now, resolved off this thread for the warm-mic recheck
+  WarmDeviceChecked { device: Option<String> },
+  // sent by the cpal error callback; fatal when the device went away
+  StreamError { capture_id: u64, error: String, fatal: bool },
+}
+
+/// Handle to the audio thread. Cheap to share; every method blocks until the
+/// audio thread has processed the request.
+pub struct RecorderService {
+  tx: Sender<RecorderCommand>,
+}
+
+impl RecorderService {
+  pub fn spawn(app: tauri::AppHandle) -> Self {
+    let (tx, rx) = crossbeam_channel::unbounded::<RecorderCommand>();
+    let cmd_tx = tx.clone();
+    thread::Builder::new()
+      .name("recorder".into())
+      .spawn(move || {
+        // built on the audio thread: it will hold streams, which are not Send
+        let state = RecorderThread {
+          app,
+          cmd_tx,
+          active: None,
+          warm: None,
+          warm_published: false,
+          warm_checked: Instant::now(),
+          warm_checking: false,
+        };
+        state.run(rx)
+      })
+      .expect("spawn recorder thread");
+
+    // open the warm-mic stream right away if it is enabled
+    let _ = tx.send(RecorderCommand::ApplySettings);
+    Self { tx }
+  }
+
+  pub fn start(&self) -> Result<String, String> {
+    self.request(|reply| RecorderCommand::Start { reply })?
+  }
+
+  pub fn stop(&self) -> Result<RecordingStopped, String> {
+    self.request(|reply| RecorderCommand::Stop { reply })?
+  }
+
+  /// Tear down the stream and writer and discard the recorded file.
+  pub fn cancel(&self) -> Result<(), String> {
+    self.request(|reply| RecorderCommand::Cancel { reply: Some(reply) })?
+  }
+
+  /// Cancel without waiting; used by the cancel hotkey.
+  pub fn cancel_nowait(&self) {
+    let _ = self.tx.send(RecorderCommand::Cancel { reply: None });
+  }
+
+  pub fn status(&self) -> Result<RecorderStatus, String> {
+    self.request(|reply| RecorderCommand::Status { reply })
+  }
+
+  /// Keep the file open but stop adding audio to it until `resume`.
+  pub fn pause(&self) -> Result<(), String> {
+    self.request(|reply| RecorderCommand::Pause { reply })?
+  }
+
+  pub fn resume(&self) -> Result<(), String> {
+    self.request(|reply| RecorderCommand::Resume { reply })?
+  }
+
+  /// Pause or resume without waiting; used by the hotkey handler.
+  pub fn toggle_pause(&self) {
+    let _ = self.tx.send(RecorderCommand::TogglePause);
+  }
+
+  /// Re-read settings that affect the idle stream (warm mic, device). Does not block.
+  pub fn apply_settings(&self) {
+    let _ = self.tx.send(RecorderCommand::ApplySettings);
+  }
+
+  fn request<T>(&self, make: impl FnOnce(Sender<T>) -> RecorderCommand) -> Result<T, String> {
+    let (reply_tx, reply_rx) = crossbeam_channel::bounded(1);
+    self
+      .tx
+      .send(make(reply_tx))
+      .map_err(|_| "recorder thread is not running".to_string())?;
+    reply_rx
+      .recv()
+      .map_err(|_| "recorder thread dropped the request".to_string())
+  }
+}
+
+/// State owned by the audio thread.
+struct RecorderThread {
+  app: tauri::AppHandle,
+  cmd_tx: Sender<RecorderCommand>,
+  active: Option<Recorder>,
+  // stream kept open between recordings (warm mic)
+  warm: Option<Capture>,
+  // last value sent to the panel's privacy indicator
+  warm_published: bool,
+  // when the warm stream's device was last compared with the preference list
+  warm_checked: Instant,
+  // a recheck is enumerating devices on its helper thread
+  warm_checking: bool,
+}
+
+impl RecorderThread {
+  fn run(mut self, rx: Receiver<RecorderCommand>) {
+    loop {
+      let cmd = match rx.recv_timeout(WARM_POLL) {
+        Ok(cmd) => cmd,
+        Err(RecvTimeoutError::Timeout) => {
+          // keep the warm stream's ring from filling up while idle
+          if let Some(warm) = self.warm.as_mut() {
+            warm.drain_to_preroll();
+          }
+          self.recheck_warm_device();
+          continue;
+        }
+        Err(RecvTimeoutError::Disconnected) => break,
+      };
+      match cmd {
+        RecorderCommand::Start { reply } => {
+          let _ = reply.send(self.start());
+        }
+        RecorderCommand::Stop { reply } => {
+          let result = match self.active.take() {
+            Some(rec) => self.finish(rec, true),
+            None => Err("Not recording".into()),
+          };
+          let _ = reply.send(result);
+        }
+        RecorderCommand::Cancel { reply } => {
+          let result = match self.active.take() {
+            Some(rec) => {
+              let path = rec.path.clone();
+              eprintln!("Cancel recording: discarding {}", path.to_string_lossy());
+              // the tail segment must not reach the pipeline after `discard`
+              let _ = rec.stop_tx.send(true);
+              // a failed finalize doesn't matter, the file is going anyway
+              let _ = self.finish(rec, true);
+              self.app.state::<SegmentPipeline>().discard(&path);
+              let removed = if path.exists() {
+                std::fs::remove_file(&path).map_err(|e| format!("remove recording: {e}"))
+              } else {
+                Ok(())
+              };
+              let _ = self.app.emit_to("panel", "dictation-cancelled", ());
+              removed
+            }
+            None => Err("Not recording".into()),
+          };
+          match reply {
+            Some(reply) => {
+              let _ = reply.send(result);
+            }
+            None => {
+              if let Err(e) = result {
+                eprintln!("Cancel hotkey: {}", e);
+              }
+            }
+          }
+        }
+        RecorderCommand::Status { reply } => {
+          let _ = reply.send(self.status());
+        }
+        RecorderCommand::Pause { reply } => {
+          let _ = reply.send(self.set_paused(true));
+        }
+        RecorderCommand::Resume { reply } => {
+          let _ = reply.send(self.set_paused(false));
+        }
+        RecorderCommand::TogglePause => {
+          let paused = self.active.as_ref().map(|rec| rec.paused_at.is_some());
+          match paused {
+            Some(paused) => {
+              let _ = self.set_paused(!paused);
+            }
+            None => eprintln!("Pause hotkey: not recording"),
+          }
+        }
+        RecorderCommand::ApplySettings => self.apply_settings(),
+        RecorderCommand::WarmDeviceChecked { device } => self.warm_device_checked(device),
+        RecorderCommand::AutoStop { path } => {
+          // ignore a late signal from a recording that was already stopped
+          if self.active.as_ref().map(|rec| rec.path != path).unwrap_or(true) {
+            continue;
+          }
+          eprintln!("Auto-stop: silence after speech, finalizing {}", path.to_string_lossy());
+          let rec = self.active.take().unwrap();
+          match self.finish(rec, true) {
+            Ok(stopped) => {
+              let _ = self.app.emit_to("panel", "recording-stopped", stopped);
+            }
+            Err(e) => {
+              eprintln!("Auto-stop failed: {}", e);
+              let _ = self.app.emit_to(
+                "panel",
+                "recording-error",
+                RecordingError {
+                  message: e,
+                  recording: None,
+                  fatal: true,
+                },
+              );
+            }
+          }
+        }
+        RecorderCommand::StreamError {
+          capture_id,
+          error,
+          fatal,
+        } => self.stream_error(capture_id, error, fatal),
+      }
+    }
+  }
+
+  fn stream_error(&mut self, capture_id: u64, error: String, fatal: bool) {
+    eprintln!("cpal stream error (capture {}): {}", capture_id, error);
+    if !fatal {
+      // the stream keeps running (an overrun, a glitch); worth a warning, not a stop
+      let _ = self.app.emit_to(
+        "panel",
+        "recording-error",
+        RecordingError {
+          message: format!("Audio stream error: {error}"),
+          recording: None,
+          fatal: false,
+        },
+      );
+      return;
+    }
+
+    if self.warm.as_ref().is_some_and(|c| c.id == capture_id) {
+      // the next recording opens (and re-resolves) the device again
+      eprintln!("Warm mic: input device lost, closing stream");
+      self.warm = None;
+      self.publish_warm(false);
+      return;
+    }
+
+    // losing the system audio source leaves the mic recording; the writer pads it with silence
+    if let Some(rec) = self.active.as_mut() {
+      if rec.system.as_ref().is_some_and(|c| c.id == capture_id) {
+        eprintln!("System audio source lost, continuing with the mic only");
+        rec.system = None;
+        return;
+      }
+    }
+
+    // errors repeat until the stream is dropped; only the first one stops the recording
+    if self.active.as_ref().map(|rec| rec.capture.id != capture_id).unwrap_or(true) {
+      return;
+    }
+    let rec = self.active.take().unwrap();
+    eprintln!("Input device lost, keeping partial recording {}", rec.path.to_string_lossy());
+
+    // the stream is dead, so never keep it as the warm mic
+    let recording = match self.finish(rec, false) {
+      Ok(stopped) => Some(stopped),
+      Err(e) => {
+        eprintln!("Finalizing partial recording failed: {}", e);
+        None
+      }
+    };
+    let _ = self.app.emit_to(
+      "panel",
+      "recording-error",
+      RecordingError {
+        message: format!("Input device error: {error}"),
+        recording,
+        fatal: true,
+      },
+    );
+  }
+
+  /// Settings, the new recording's path and the system audio capture.
+  fn prepare_start(&self) -> Result<(AppSettings, PathBuf, Option<Capture>), String> {
+    let settings = get_settings(self.app.clone())?;
+
+    // choose an app cache dir for temp wav; nothing is being written yet, so
+    // this is a safe point to drop recordings past the retention limits
+    let cache_dir = retention::recordings_dir(&self.app)?;
+    retention::sweep(&cache_dir, &settings);
+    let path = cache_dir.join(format!("dictation-{}.wav", uuid::Uuid::new_v4()));
+
+    let system_mode = audio::SystemAudioMode::from_setting(&settings.system_audio);
+    let system = match system_mode {
+      audio::SystemAudioMode::Off => None,
+      _ => Some(open_system_capture(&settings, &self.cmd_tx)?),
+    };
+    Ok((settings, path, system))
+  }
+
+  /// Idle only: look up the device the preferences pick now on a helper
+  /// thread; `warm_device_checked` gets the answer.
+  fn recheck_warm_device(&mut self) {
+    if self.warm.is_none() || self.warm_checking || self.warm_checked.elapsed() < WARM_RECHECK {
+      return;
+    }
+    let Ok(settings) = get_settings(self.app.clone()) else {
+      return;
+    };
+    let cmd_tx = self.cmd_tx.clone();
+    let spawned = thread::Builder::new().name("warm-mic-check".into()).spawn(move || {
+      let host = devices::host(&settings.audio_host);
+      let device = devices::choose_input_device(&host, &settings.input_devices)
+        .ok()
+        .map(|(_, choice)| choice.device);
+      let _ = cmd_tx.send(RecorderCommand::WarmDeviceChecked { device });
+    });
+    match spawned {
+      Ok(_) => self.warm_checking = true,
+      Err(e) => {
+        eprintln!("Warm mic: failed to spawn device check: {}", e);
+        self.warm_checked = Instant::now();
+      }
+    }
+  }
+
+  /// Reopen the warm stream when it is no longer on the preferred device; a
+  /// headset may have been plugged in or a dock disconnected since it opened.
+  fn warm_device_checked(&mut self, device: Option<String>) {
+    self.warm_checking = false;
+    self.warm_checked = Instant::now();
+    // a recording that started meanwhile took the warm stream; park() decides afterwards
+    let Some(warm) = &self.warm else {
+      return;
+    };
+    match device {
+      Some(device) if device != warm.choice.device => {
+        eprintln!(
+          "Warm mic: {} is no longer the preferred device ({} is), reopening",
+          warm.choice.device, device
+        );
+        self.apply_settings();
+      }
+      _ => {}
+    }
+  }
+
+  fn set_paused(&mut self, pause: bool) -> Result<(), String> {
+    let rec = self.active.as_mut().ok_or("Not recording")?;
+    if rec.paused_at.is_some() == pause {
+      return Ok(());
+    }
+    rec.capture.shared.paused.store(pause, Ordering::Relaxed);
+    if let Some(system) = &rec.system {
+      system.shared.paused.store(pause, Ordering::Relaxed);
+    }
+    if pause {
+      rec.paused_at = Some(Instant::now());
+    } else if let Some(paused_at) = rec.paused_at.take() {
+      rec.paused_total += paused_at.elapsed();
+    }
+    eprintln!(
+      "{} recording {}",
+      if pause { "Pause" } else { "Resume" },
+      rec.path.to_string_lossy()
+    );
+    let _ = self.app.emit_to("panel", "recording-paused", pause);
+    Ok(())
+  }
+
+  fn status(&self) -> RecorderStatus {
+    match &self.active {
+      Some(rec) => RecorderStatus {
+        recording: true,
+        path: Some(rec.path.to_string_lossy().to_string()),
+        sample_rate: Some(rec.sample_rate),
+        elapsed_ms: rec.active_elapsed().as_millis() as u64,
+        paused: rec.paused_at.is_some(),
+        warm_mic: self.warm_published,
+      },
+      None => RecorderStatus {
+        recording: false,
+        path: None,
+        sample_rate: None,
+        elapsed_ms: 0,
+        paused: false,
+        warm_mic: self.warm_published,
+      },
+    }
+  }
+
+  fn start(&mut self) -> Result<String, String> {
+    if self.active.is_some() {
+      return Err("Already recording".into());
+    }
+
+    // the hotkey moment: a warm stream's buffered audio becomes the pre-roll
+    // right away, and from here on the ring holds everything for the writer,
+    // however long the steps below take
+    let mut warm = self.warm.take();
+    if let Some(capture) = warm.as_mut() {
+      capture.drain_to_preroll();
+    }
+
+    let prepared = self.prepare_start();
+    let (settings, path, system) = match prepared {
+      Ok(prepared) => prepared,
+      Err(e) => {
+        self.warm = warm;
+        return Err(e);
+      }
+    };
+
+    // a warm stream is already running, so there is no device-open delay
+    let capture = match warm {
+      Some(capture) => capture,
+      None => {
+        let mut capture = open_capture(&settings, &self.cmd_tx)?;
+        // everything since the device opened becomes pre-roll
+        capture.drain_to_preroll();
+        capture
+      }
+    };
+    let _ = self.app.emit_to("panel", "input-device", capture.choice.clone());
+
+    let rec = start_writer(&self.app, &self.cmd_tx, &settings, capture, system, path)?;
+    let path = rec.path.to_string_lossy().to_string();
+    self.active = Some(rec);
+    crate::set_recording_shortcuts(&self.app, true);
+    Ok(path)
+  }
+
+  /// Finalize the recording. `reuse_stream` is false when the stream failed and
+  /// must not be kept as the warm mic.
+  fn finish(&mut self, rec: Recorder, reuse_stream: bool) -> Result<RecordingStopped, String> {
+    let Recorder {
+      path,
+      mut capture,
+      system,
+      writer_join,
+      stop_tx,
+      overruns_at_start,
+      dropped_at_start,
+      sample_rate,
+      ..
+    } = rec;
+    eprintln!("Stop recording: stopping writer for {}", path.to_string_lossy());
+    crate::set_recording_shortcuts(&self.app, false);
+
+    // the writer drains what the callbacks have queued, then finalizes
+    drop(stop_tx);
+    let joined = writer_join.join();
+    drop(system);
+    capture.shared.paused.store(false, Ordering::Relaxed);
+
+    let shared = &capture.shared;
+    let overruns = shared.overruns.load(Ordering::Relaxed) - overruns_at_start;
+    let dropped = shared.dropped_samples.load(Ordering::Relaxed) - dropped_at_start;
+    let dropped_ms = dropped * 1000 / capture.device_rate as u64;
+    eprintln!(
+      "Stop recording: {} callbacks so far, {} overruns ({} ms dropped) in this recording",
+      shared.callbacks.load(Ordering::Relaxed),
+      overruns,
+      dropped_ms
+    );
+
+    // the mic stream may stay warm, but only with its ring reader back
+    let Written { stats, segments } = match joined {
+      Ok((consumer, written)) => {
+        capture.consumer = Some(consumer);
+        if reuse_stream {
+          self.park(capture);
+        } else {
+          drop(capture);
+          self.publish_warm(false);
+        }
+        written.map_err(|e| format!("writer failed: {e}"))?
+      }
+      Err(_) => {
+        drop(capture);
+        self.publish_warm(false);
+        return Err("writer thread panicked".to_string());
+      }
+    };
+
+    // On Windows, wait a bit for file system to catch up
+    #[cfg(windows)]
+    {
+      std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+
+    // Verify file exists and has content
+    if !path.exists() {
+      return Err(format!("Recorded file does not exist: {}", path.to_string_lossy()));
+    }
+
+    let file_size = std::fs::metadata(&path)
+      .map_err(|e| format!("get file metadata: {e}"))?
+      .len();
+
+    eprintln!("Stop recording: file written, size: {} bytes", file_size);
+
+    if file_size == 0 {
+      return Err("Recorded file is empty".into());
+    }
+
+    eprintln!(
+      "Stop recording: {} ms, peak {:.1} dBFS, rms {:.1} dBFS, {} clipped samples, {:.0}% silence",
+      stats.duration_ms,
+      stats.peak_db,
+      stats.rms_db,
+      stats.clipped_samples,
+      stats.silence_ratio * 100.0
+    );
+
+    Ok(RecordingStopped {
+      path: path.to_string_lossy().to_string(),
+      sample_rate,
+      stats,
+      overruns,
+      dropped_ms,
+      segments,
+    })
+  }
+
+  /// After a recording: keep the stream as the warm mic, or drop it to close the device.
+  fn park(&mut self, mut capture: Capture) {
+    let keep_warm = get_settings(self.app.clone())
+      .map(|s| s.warm_mic)
+      .unwrap_or(false);
+    if keep_warm {
+      // don't carry the end of this dictation into the next one
+      capture.drain_to_preroll();
+      capture.preroll.clear();
+      self.warm = Some(capture);
+    } else {
+      drop(capture);
+    }
+    self.publish_warm(keep_warm);
+  }
+
+  fn apply_settings(&mut self) {
+    let settings = match get_settings(self.app.clone()) {
+      Ok(s) => s,
+      Err(e) => {
+        eprintln!("Recorder: failed to load settings: {}", e);
+        return;
+      }
+    };
+
+    // reopen so device and pre-roll length changes take effect
+    self.warm = None;
+    if settings.warm_mic && self.active.is_none() {
+      match open_capture(&settings, &self.cmd_tx) {
+        Ok(capture) => {
+          eprintln!("Warm mic: input stream open, keeping {} ms pre-roll", settings.preroll_ms);
+          self.warm = Some(capture);
+        }
+        Err(e) => eprintln!("Warm mic: failed to open input stream: {}", e),
+      }
+    }
+    // while recording the stream is live anyway; park() decides afterwards
+    let warm = self.warm.is_some() || (settings.warm_mic && self.active.is_some());
+    self.publish_warm(warm);
+  }
+
+  /// Privacy indicator: tell the panel whether the mic is open while idle.
+  fn publish_warm(&mut self, warm: bool) {
+    if warm != self.warm_published {
+      self.warm_published = warm;
+      let _ = self.app.emit_to("panel", "warm-mic", warm);
+    }
+  }
+}
+
+fn open_capture(settings: &AppSettings, cmd_tx: &Sender<RecorderCommand>) -> Result<Capture, String> {
+  let host = devices::host(&settings.audio_host);
+  let (device, choice) = devices::choose_input_device(&host, &settings.input_devices)?;
+  if choice.fallback {
+    eprintln!("Warning: {}", choice.reason);
+  }
+  eprintln!("Open capture: using input device: {} ({})", choice.device, choice.reason);
+
+  let saved = settings.input_configs.get(&devices::config_key(host.id().name(), &choice.device));
+  let config = devices::stream_config(&device, saved)?;
+  let select = ChannelSelect::from_setting(settings.input_channel, config.channels() as usize);
+
+  // a cold stream only buffers what arrives before the writer is wired up,
+  // which is all audio since the device opened, so it is safe to prepend too
+  let preroll_ms = if settings.warm_mic { settings.preroll_ms } else { 1000 };
+  open_stream(device, choice, config, select, preroll_ms, cmd_tx)
+}
+
+/// Open the monitor/loopback source that carries what the system is playing.
+fn open_system_capture(settings: &AppSettings, cmd_tx: &Sender<RecorderCommand>) -> Result<Capture, String> {
+  let host = devices::host(&settings.audio_host);
+  let (device, choice) = devices::choose_loopback_device(&host, &settings.system_audio_device)?;
+  if choice.fallback {
+    eprintln!("Warning: {}", choice.reason);
+  }
+  eprintln!("Open capture: using system audio source: {} ({})", choice.device, choice.reason);
+
+  let saved = settings.input_configs.get(&devices::config_key(host.id().name(), &choice.device));
+  let config = devices::stream_config(&device, saved)?;
+  // system audio is always downmixed; the channel setting is for the mic
+  open_stream(device, choice, config, ChannelSelect::Downmix, 0, cmd_tx)
+}
+
+fn open_stream(
+  device: cpal::Device,
+  choice: devices::DeviceChoice,
+  config: cpal::SupportedStreamConfig,
+  select: ChannelSelect,
+  preroll_ms: u32,
+  cmd_tx: &Sender<RecorderCommand>,
+) -> Result<Capture, String> {
+  let device_rate = config.sample_rate().0;
+  let channels = config.channels() as usize;
+
+  eprintln!("Open capture: device rate: {}, channels: {}, format: {:?}, channel select: {:?}", device_rate, channels, config.sample_format(), select);
+
+  let preroll_len = (device_rate as u64 * preroll_ms as u64 / 1000) as usize;
+  let shared = Arc::new(CaptureShared {
+    paused: AtomicBool::new(false),
+    callbacks: AtomicU64::new(0),
+    overruns: AtomicU64::new(0),
+    dropped_samples: AtomicU64::new(0),
+  });
+  // allocated once here; the callback only ever writes into it
+  let (producer, consumer) = rtrb::RingBuffer::new(device_rate as usize * RING_SECONDS);
+
+  // audio callback: downmix to mono f32 straight into the ring
+  let callback = CaptureCallback {
+    shared: shared.clone(),
+    producer,
+    channels,
+    select,
+  };
+
+  // error callback: hand the error to the recorder thread, which owns the stream
+  let id = NEXT_CAPTURE_ID.fetch_add(1, Ordering::Relaxed);
+  let err_tx = cmd_tx.clone();
+  let on_error = move |err: cpal::StreamError| {
+    let fatal = matches!(err, cpal::StreamError::DeviceNotAvailable);
+    let _ = err_tx.send(RecorderCommand::StreamError {
+      capture_id: id,
+      error: err.to_string(),
+      fatal,
+    });
+  };
+
+  let stream_config: cpal::StreamConfig = config.clone().into();
+  let stream = match config.sample_format() {
+    cpal::SampleFormat::I8 => build_input_stream::<i8>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::I16 => build_input_stream::<i16>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::I32 => build_input_stream::<i32>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::I64 => build_input_stream::<i64>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::U8 => build_input_stream::<u8>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::U16 => build_input_stream::<u16>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::U32 => build_input_stream::<u32>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::U64 => build_input_stream::<u64>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::F32 => build_input_stream::<f32>(&device, &stream_config, callback, on_error),
+    cpal::SampleFormat::F64 => build_input_stream::<f64>(&device, &stream_config, callback, on_error),
+    other => return Err(format!("Unsupported sample format: {other}")),
+  }?;
+
+  stream.play().map_err(|e| format!("stream play: {e}"))?;
+
+  Ok(Capture {
+    _stream: stream,
+    id,
+    shared,
+    consumer: Some(consumer),
+    preroll: audio::PrerollBuffer::new(preroll_len),
+    device_rate,
+    choice,
+  })
+}
+
+/// Spawn the writer thread for a new recording and hand it the capture's ring
+/// (and the system audio capture's, if any), starting with whatever pre-roll
+/// the stream has buffered.
+fn start_writer(
+  app: &tauri::AppHandle,
+  cmd_tx: &Sender<RecorderCommand>,
+  settings: &AppSettings,
+  mut capture: Capture,
+  mut system: Option<Capture>,
+  path: PathBuf,
+) -> Result<Recorder, String> {
+  let device_rate = capture.device_rate;
+  let sample_rate = audio::output_rate(device_rate, settings.target_sample_rate);
+  eprintln!("Start recording: device rate: {}, output rate: {}", device_rate, sample_rate);
+
+  let mut mic = capture
+    .consumer
+    .take()
+    .ok_or("input stream is already feeding a recording")?;
+  let preroll = capture.preroll.take();
+  let preroll_ms = preroll.len() as u64 * 1000 / device_rate as u64;
+  if preroll_ms > 0 {
+    eprintln!("Start recording: prepending {} ms of pre-roll", preroll_ms);
+  }
+
+  let system_mode = match system {
+    Some(_) => audio::SystemAudioMode::from_setting(&settings.system_audio),
+    None => audio::SystemAudioMode::Off,
+  };
+  let (mut system_ring, system_resampler, system_silence) = match system.as_mut() {
+    Some(system) => {
+      let mut consumer = system
+        .consumer
+        .take()
+        .ok_or("system audio stream is already feeding a recording")?;
+      // the system source has no pre-roll; pad it so both start at the same instant
+      let _ = drain_ring(&mut consumer, |_| -> Result<(), ()> { Ok(()) });
+      let silence = vec![0.0; (system.device_rate as u64 * preroll_ms / 1000) as usize];
+      // the system source is brought to the mic's output rate so frames line up
+      let resampler = audio::MonoResampler::new(system.device_rate, sample_rate)?;
+      (Some(consumer), Some(resampler), silence)
+    }
+    None => (None, None, Vec::new()),
+  };
+
+  let spec = hound::WavSpec {
+    channels: system_mode.channels(),
+    sample_rate,
+    bits_per_sample: 16,
+    sample_format: hound::SampleFormat::Int,
+  };
+  let mut writer = RecordingWriter {
+    out: WavOutput {
+      writer: hound::WavWriter::create(&path, spec).map_err(|e| format!("wav create: {e}"))?,
+      // stats cover exactly what lands in the file (mixed to mono), at the output rate
+      stats: audio::StatsAccumulator::new(sample_rate),
+      meter: audio::LevelMeter::new(sample_rate),
+      app: app.clone(),
+      unflushed: 0,
+      flush_every: sample_rate as usize,
+      segments: settings.streaming_transcription.then(|| SegmentOutput {
+        recording: path.clone(),
+        spec,
+        splitter: audio::SegmentSplitter::new(
+          sample_rate,
+          audio::VadConfig {
+            threshold_db: settings.vad_threshold_db,
+            silence_ms: settings.segment_pause_ms,
+          },
+          SEGMENT_MIN_MS,
+          settings.segment_max_ms,
+        ),
+        current: None,
+        count: 0,
+        cuts: Vec::new(),
+        app: app.clone(),
+      }),
+    },
+    resampler: audio::MonoResampler::new(device_rate, sample_rate)?,
+    system_resampler,
+    // half a second of one side without the other means that source has stalled
+    mixer: system_ring
+      .is_some()
+      .then(|| audio::SourceMixer::new(system_mode, sample_rate as usize / 2)),
+    vad: settings.vad_auto_stop.then(|| {
+      audio::VoiceActivityDetector::new(
+        device_rate,
+        audio::VadConfig {
+          threshold_db: settings.vad_threshold_db,
+          silence_ms: settings.vad_silence_ms,
+        },
+      )
+    }),
+    auto_stop_tx: cmd_tx.clone(),
+    path: path.clone(),
+    resampled: Vec::new(),
+    file: Vec::new(),
+    mono: Vec::new(),
+  };
+
+  let (stop_tx, stop_rx) = crossbeam_channel::bounded::<bool>(1);
+  // writer thread: drain the rings every few ms, resample mono f32 to the output
+  // rate, line up system audio, meter and write i16 PCM to WAV in batches
+  let writer_join = thread::spawn(move || -> WriterOutput {
+    let result = (|| -> anyhow::Result<Written> {
+      writer.push_mic(&preroll)?;
+      writer.push_system(&system_silence)?;
+      let discard = loop {
+        // check before draining so the last drain happens after the stop request
+        let stop = match stop_rx.recv_timeout(WRITER_POLL) {
+          Err(RecvTimeoutError::Timeout) => None,
+          Ok(discard) => Some(discard),
+          Err(RecvTimeoutError::Disconnected) => Some(false),
+        };
+        drain_ring(&mut mic, |samples| writer.push_mic(samples))?;
+        if let Some(ring) = system_ring.as_mut() {
+          drain_ring(ring, |samples| writer.push_system(samples))?;
+        }
+        writer.mix(false)?;
+        if let Some(discard) = stop {
+          break discard;
+        }
+      };
+      writer.finish(discard)
+    })();
+    (mic, result)
+  });
+
+  let shared = &capture.shared;
+  Ok(Recorder {
+    path,
+    overruns_at_start: shared.overruns.load(Ordering::Relaxed),
+    dropped_at_start: shared.dropped_samples.load(Ordering::Relaxed),
+    capture,
+    system,
+    writer_join,
+    stop_tx,
+    sample_rate,
+    started_at: Instant::now(),
+    paused_at: None,
+    paused_total: Duration::ZERO,
+  })
+}
+
+type WavFileWriter = hound::WavWriter<std::io::BufWriter<std::fs::File>>;
+
+/// Append normalised samples to `writer` as 16-bit PCM in one batch: a single
+/// bounds check and buffered write instead of one per sample.
+fn write_pcm(writer: &mut WavFileWriter, samples: &[f32]) -> hound::Result<()> {
+  if samples.is_empty() {
+    return Ok(());
+  }
+  let mut batch = writer.get_i16_writer(samples.len() as u32);
+  for &s in samples {
+    batch.write_sample(audio::f32_to_i16(s));
+  }
+  batch.flush()
+}
+
+/// The WAV file being written, with the meter and stats that watch it.
+struct WavOutput {
+  writer: WavFileWriter,
+  stats: audio::StatsAccumulator,
+  meter: audio::LevelMeter,
+  app: tauri::AppHandle,
+  // checkpoint the header about once a second so a crash loses at most that much
+  unflushed: usize,
+  flush_every: usize,
+  // streaming transcription: the same audio, cut into segment files
+  segments: Option<SegmentOutput>,
+}
+
+impl WavOutput {
+  /// Write `file` (interleaved, as stored) whose frames mixed to mono are `mono`.
+  fn write(&mut self, file: &[f32], mono: &[f32]) -> anyhow::Result<()> {
+    // live input meter for the panel, throttled by the meter window
+    if let Some(level) = self.meter.push(mono) {
+      let _ = self.app.emit_to("panel", "recording-level", level);
+    }
+    self.stats.push(mono);
+    write_pcm(&mut self.writer, file)?;
+    self.unflushed += mono.len();
+    if self.unflushed >= self.flush_every {
+      self.writer.flush()?;
+      self.unflushed = 0;
+    }
+    if let Some(segments) = self.segments.as_mut() {
+      segments.write(file, mono)?;
+    }
+    Ok(())
+  }
+}
+
+/// Segment files written alongside the recording and handed, one by one as
+/// they close, to the background transcription pipeline.
+struct SegmentOutput {
+  recording: PathBuf,
+  spec: hound::WavSpec,
+  splitter: audio::SegmentSplitter,
+  current: Option<WavFileWriter>,
+  // segments closed so far; also the index of the next one
+  count: usize,
+  cuts: Vec<usize>,
+  app: tauri::AppHandle,
+}
+
+impl SegmentOutput {
+  fn write(&mut self, file: &[f32], mono: &[f32]) -> anyhow::Result<()> {
+    let channels = self.spec.channels as usize;
+    self.cuts.clear();
+    self.splitter.push(mono, &mut self.cuts);
+    let mut start = 0;
+    for i in 0..=self.cuts.len() {
+      let end = self.cuts.get(i).copied().unwrap_or(mono.len());
+      if end > start {
+        let writer = match self.current.as_mut() {
+          Some(writer) => writer,
+          None => self.current.insert(hound::WavWriter::create(
+            segments::segment_path(&self.recording, self.count),
+            self.spec,
+          )?),
+        };
+        write_pcm(writer, &file[start * channels..end * channels])?;
+      }
+      if i < self.cuts.len() {
+        self.close(false)?;
+      }
+      start = end;
+    }
+    Ok(())
+  }
+
+  /// Finalize the open segment and queue it for transcription, or delete it
+  /// when the recording is being discarded.
+  fn close(&mut self, discard: bool) -> anyhow::Result<()> {
+    if let Some(writer) = self.current.take() {
+      writer.finalize()?;
+      let path = segments::segment_path(&self.recording, self.count);
+      if discard {
+        let _ = std::fs::remove_file(&path);
+        return Ok(());
+      }
+      eprintln!("Segment {} closed: {}", self.count, path.to_string_lossy());
+      self
+        .app
+        .state::<SegmentPipeline>()
+        .submit(&self.app, &self.recording, self.count, path);
+      self.count += 1;
+    }
+    Ok(())
+  }
+}
+
+/// Everything between the rings and the file, owned by the writer thread.
+struct RecordingWriter {
+  out: WavOutput,
+  resampler: audio::MonoResampler,
+  system_resampler: Option<audio::MonoResampler>,
+  // lines up mic and system audio; None when recording the mic only
+  mixer: Option<audio::SourceMixer>,
+  vad: Option<audio::VoiceActivityDetector>,
+  auto_stop_tx: Sender<RecorderCommand>,
+  path: PathBuf,
+  resampled: Vec<f32>,
+  file: Vec<f32>,
+  mono: Vec<f32>,
+}
+
+impl RecordingWriter {
+  fn push_mic(&mut self, samples: &[f32]) -> anyhow::Result<()> {
+    // hands-free mode: ask the recorder to stop once speech is followed by
+    // silence; only the mic counts, playback must not keep it going
+    if let Some(detector) = self.vad.as_mut() {
+      if detector.push(samples) {
+        let _ = self.auto_stop_tx.send(RecorderCommand::AutoStop {
+          path: self.path.clone(),
+        });
+        self.vad = None;
+      }
+    }
+    self.resampled.clear();
+    self
+      .resampler
+      .process(samples, &mut self.resampled)
+      .map_err(anyhow::Error::msg)?;
+    match self.mixer.as_mut() {
+      Some(mixer) => mixer.push_mic(&self.resampled),
+      None => self.out.write(&self.resampled, &self.resampled)?,
+    }
+    Ok(())
+  }
+
+  fn push_system(&mut self, samples: &[f32]) -> anyhow::Result<()> {
+    if let (Some(mixer), Some(resampler)) = (self.mixer.as_mut(), self.system_resampler.as_mut()) {
+      self.resampled.clear();
+      resampler
+        .process(samples, &mut self.resampled)
+        .map_err(anyhow::Error::msg)?;
+      mixer.push_system(&self.resampled);
+    }
+    Ok(())
+  }
+
+  /// Write the frames both sources have delivered; `flush` writes the rest.
+  fn mix(&mut self, flush: bool) -> anyhow::Result<()> {
+    if let Some(mixer) = self.mixer.as_mut() {
+      self.file.clear();
+      self.mono.clear();
+      mixer.drain(flush, &mut self.file, &mut self.mono);
+      self.out.write(&self.file, &self.mono)?;
+    }
+    Ok(())
+  }
+
+  /// Flush and finalize the file. `discard` keeps the tail segment away from
+  /// the pipeline, for a cancelled recording.
+  fn finish(mut self, discard: bool) -> anyhow::Result<Written> {
+    self.resampled.clear();
+    self
+      .resampler
+      .flush(&mut self.resampled)
+      .map_err(anyhow::Error::msg)?;
+    match self.mixer.as_mut() {
+      Some(mixer) => {
+        mixer.push_mic(&self.resampled);
+        if let Some(resampler) = self.system_resampler.as_mut() {
+          self.resampled.clear();
+          resampler
+            .flush(&mut self.resampled)
+            .map_err(anyhow::Error::msg)?;
+          mixer.push_system(&self.resampled);
+        }
+        self.mix(true)?;
+      }
+      None => self.out.write(&self.resampled, &self.resampled)?,
+    }
+
+    // the tail: the only segment still to transcribe when the recording stops
+    let mut segments = 0;
+    if let Some(mut output) = self.out.segments.take() {
+      output.close(discard)?;
+      segments = output.count;
+    }
+
+    let stats = self.out.stats.finish();
+    eprintln!("Writer thread: wrote {} total samples", stats.samples);
+    self.out.writer.finalize()?;
+    Ok(Written { stats, segments })
+  }
+}
+
+/// Result of `test_input_device`: what a short capture from one device sounded like.
+#[derive(Serialize)]
+pub struct DeviceTest {
+  pub device: String,
+  pub sample_rate: u32,
+  #[serde(flatten)]
+  pub stats: audio::RecordingStats,
+  // 16-bit mono WAV of the capture, for playback in Settings
+  pub wav_base64: String,
+}
+
+/// Capture `duration_ms` from the device the settings pick, on the calling
+/// thread, through the same callback and conversion path as a recording but
+/// without the recorder thread or a file on disk.
+pub fn test_capture(settings: &AppSettings, duration_ms: u32) -> Result<DeviceTest, String> {
+  use base64::Engine;
+
+  let (err_tx, err_rx) = crossbeam_channel::unbounded::<RecorderCommand>();
+  let mut capture = open_capture(settings, &err_tx)?;
+  let device_rate = capture.device_rate;
+  let mut resampler =
+    audio::MonoResampler::new(device_rate, audio::output_rate(device_rate, settings.target_sample_rate))?;
+
+  let mut ring = capture.consumer.take().ok_or("input stream has no reader")?;
+  // drop what arrived while the device was opening; the test starts now
+  let _ = drain_ring(&mut ring, |_| -> Result<(), ()> { Ok(()) });
+
+  let wanted = device_rate as usize * duration_ms as usize / 1000;
+  let deadline = Instant::now() + std::time::Duration::from_millis(duration_ms as u64 + 2000);
+  let mut captured = Vec::with_capacity(wanted);
+  while captured.len() < wanted {
+    if let Ok(RecorderCommand::StreamError { error, .. }) = err_rx.try_recv() {
+      return Err(format!("Input device error: {error}"));
+    }
+    if Instant::now() >= deadline {
+      return Err("No audio received from the input device".into());
+    }
+    thread::sleep(WRITER_POLL);
+    let _ = drain_ring(&mut ring, |samples| -> Result<(), ()> {
+      captured.extend_from_slice(samples);
+      Ok(())
+    });
+  }
+  // stop capture before the (comparatively slow) processing below
+  let device = capture.choice.device.clone();
+  drop(capture);
+  captured.truncate(wanted);
+
+  let mut samples = Vec::new();
+  resampler.process(&captured, &mut samples)?;
+  resampler.flush(&mut samples)?;
+  let mut stats = audio::StatsAccumulator::new(resampler.output_rate());
+  stats.push(&samples);
+
+  let spec = hound::WavSpec {
+    channels: 1,
+    sample_rate: resampler.output_rate(),
+    bits_per_sample: 16,
+    sample_format: hound::SampleFormat::Int,
+  };
+  let mut wav = std::io::Cursor::new(Vec::new());
+  {
+    let mut writer = hound::WavWriter::new(&mut wav, spec).map_err(|e| format!("wav create: {e}"))?;
+    for &s in &samples {
+      writer
+        .write_sample(audio::f32_to_i16(s))
+        .map_err(|e| format!("wav write: {e}"))?;
+    }
+    writer.finalize().map_err(|e| format!("wav finalize: {e}"))?;
+  }
+
+  Ok(DeviceTest {
+    device,
+    sample_rate: spec.sample_rate,
+    stats: stats.finish(),
+    wav_base64: base64::engine::general_purpose::STANDARD.encode(wav.into_inner()),
+  })
+}
+
+/// State moved into the cpal data callback, independent of the device sample format.
+struct CaptureCallback {
+  shared: Arc<CaptureShared>,
+  producer: rtrb::Producer<f32>,
+  channels: usize,
+  select: ChannelSelect,
+}
+
+impl CaptureCallback {
+  /// Runs on the audio thread: no locks, no allocation, no logging.
+  fn on_data<T>(&mut self, data: &[T])
+  where
+    T: cpal::Sample,
+    f32: cpal::FromSample<T>,
+  {
+    self.shared.callbacks.fetch_add(1, Ordering::Relaxed);
+    // paused: drop the audio so the file only holds the active segments
+    if self.shared.paused.load(Ordering::Relaxed) {
+      return;
+    }
+
+    let frames = data.len() / self.channels;
+    let fits = frames.min(self.producer.slots());
+    if fits < frames {
+      // the reader fell behind; keep what is queued and drop the newest audio
+      self.shared.overruns.fetch_add(1, Ordering::Relaxed);
+      self
+        .shared
+        .dropped_samples
+        .fetch_add((frames - fits) as u64, Ordering::Relaxed);
+    }
+    if let Ok(chunk) = self.producer.write_chunk_uninit(fits) {
+      chunk.fill_from_iter(audio::downmix_iter(
+        &data[..fits * self.channels],
+        self.channels,
+        self.select,
+      ));
+    }
+  }
+}
+
+fn build_input_stream<T>(
+  device: &cpal::Device,
+  config: &cpal::StreamConfig,
+  mut callback: CaptureCallback,
+  on_error: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, String>
+where
+  T: cpal::SizedSample,
+  f32: cpal::FromSample<T>,
+{
+  device
+    .build_input_stream(config, move |data: &[T], _| callback.on_data(data), on_error, None)
+    .map_err(|e| format!("build stream: {e}"))
+}
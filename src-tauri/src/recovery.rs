@@ -0,0 +1,246 @@
+// src-tauri/src/recovery.rs
+//
+// Crash recovery for recordings. The writer flushes the WAV header about once
+// a second, so a recording cut short by a crash is valid up to that point and
+// only the audio written since is unaccounted for. At startup the header is
+// rewritten to cover everything that made it to disk, and a marker file next to
+// the recording keeps it offered (and out of retention) until it is
+// transcribed or discarded, across restarts.
+use serde::Serialize;
+use std::{
+  fs::OpenOptions,
+  io::{Read, Seek, SeekFrom, Write},
+  path::{Path, PathBuf},
+};
+
+/// A recording whose header was repaired, offered for transcription.
+#[derive(Serialize, Clone, Debug)]
+pub struct RecoveredRecording {
+  pub path: String,
+  pub sample_rate: u32,
+  pub duration_ms: u64,
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+  u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+/// Make the RIFF and data chunk sizes match what is actually in the file,
+/// dropping a trailing partial frame. Returns None if the header was already
+/// right (a normally finalized file).
+pub fn repair_wav_header(path: &Path) -> Result<Option<RecoveredRecording>, String> {
+  let mut file = OpenOptions::new()
+    .read(true)
+    .write(true)
+    .open(path)
+    .map_err(|e| format!("open: {e}"))?;
+  let file_len = file.metadata().map_err(|e| format!("metadata: {e}"))?.len();
+
+  // hound writes RIFF/WAVE, a fmt chunk and the data chunk in the first few dozen bytes
+  let mut head = vec![0u8; file_len.min(4096) as usize];
+  file.read_exact(&mut head).map_err(|e| format!("read header: {e}"))?;
+  if head.len() < 12 || &head[0..4] != b"RIFF" || &head[8..12] != b"WAVE" {
+    return Err("not a RIFF/WAVE file".into());
+  }
+
+  let mut pos = 12;
+  let mut format = None;
+  let data_header = loop {
+    if pos + 8 > head.len() {
+      return Err("no data chunk".into());
+    }
+    let id = &head[pos..pos + 4];
+    let size = u32_at(&head, pos + 4) as usize;
+    if id == b"fmt " {
+      if pos + 8 + 16 > head.len() {
+        return Err("truncated fmt chunk".into());
+      }
+      let body = pos + 8;
+      let sample_rate = u32_at(&head, body + 4);
+      let block_align = u16::from_le_bytes([head[body + 12], head[body + 13]]);
+      format = Some((sample_rate, block_align.max(1) as u64));
+    }
+    if id == b"data" {
+      break pos;
+    }
+    // chunks are padded to an even size
+    pos += 8 + size + (size & 1);
+  };
+  let (sample_rate, block_align) = format.ok_or("no fmt chunk before data")?;
+
+  let data_start = data_header as u64 + 8;
+  let data_len = file_len.saturating_sub(data_start) / block_align * block_align;
+  let riff_len = data_start + data_len - 8;
+  if u32_at(&head, data_header + 4) as u64 == data_len && u32_at(&head, 4) as u64 == riff_len {
+    return Ok(None);
+  }
+
+  file
+    .set_len(data_start + data_len)
+    .map_err(|e| format!("truncate: {e}"))?;
+  file.seek(SeekFrom::Start(4)).map_err(|e| format!("seek: {e}"))?;
+  file
+    .write_all(&(riff_len as u32).to_le_bytes())
+    .map_err(|e| format!("write RIFF size: {e}"))?;
+  file
+    .seek(SeekFrom::Start(data_header as u64 + 4))
+    .map_err(|e| format!("seek: {e}"))?;
+  file
+    .write_all(&(data_len as u32).to_le_bytes())
+    .map_err(|e| format!("write data size: {e}"))?;
+  file.flush().map_err(|e| format!("flush: {e}"))?;
+
+  Ok(Some(RecoveredRecording {
+    path: path.to_string_lossy().to_string(),
+    sample_rate,
+    duration_ms: data_len / block_align * 1000 / sample_rate.max(1) as u64,
+  }))
+}
+
+/// Marker next to a recovered recording that is still waiting for the user.
+fn marker_path(path: &Path) -> PathBuf {
+  let mut name = path.as_os_str().to_owned();
+  name.push(".recovered");
+  PathBuf::from(name)
+}
+
+/// True while `path` is a recovered recording nobody has transcribed or
+/// discarded yet; retention leaves these alone.
+pub fn is_pending(path: &Path) -> bool {
+  marker_path(path).exists()
+}
+
+/// The recording at `path` was transcribed or discarded; stop offering it.
+pub fn resolve(path: &Path) {
+  let marker = marker_path(path);
+  if let Err(e) = std::fs::remove_file(&marker) {
+    if e.kind() != std::io::ErrorKind::NotFound {
+      eprintln!("Recovery: failed to remove {}: {}", marker.to_string_lossy(), e);
+    }
+  }
+}
+
+/// Repair every unfinalized recording among `files` and mark it pending. Must
+/// run before the recorder starts, when none of them can still be open for
+/// writing.
+pub fn recover(files: impl IntoIterator<Item = PathBuf>) -> usize {
+  let mut repaired = 0;
+  for path in files {
+    match repair_wav_header(&path) {
+      Ok(Some(recording)) if recording.duration_ms > 0 => {
+        eprintln!(
+          "Recovery: repaired {} ({} ms)",
+          recording.path, recording.duration_ms
+        );
+        match std::fs::write(marker_path(&path), b"") {
+          Ok(_) => repaired += 1,
+          Err(e) => eprintln!("Recovery: failed to mark {}: {}", recording.path, e),
+        }
+      }
+      Ok(_) => {}
+      Err(e) => eprintln!("Recovery: skipping {}: {}", path.to_string_lossy(), e),
+    }
+  }
+  repaired
+}
+
+/// Recordings among `files` still pending from this or an earlier start.
+/// Markers whose recording is gone are removed.
+pub fn pending(dir: &Path, files: impl IntoIterator<Item = PathBuf>) -> Vec<RecoveredRecording> {
+  let files: Vec<PathBuf> = files.into_iter().collect();
+  if let Ok(entries) = std::fs::read_dir(dir) {
+    for marker in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+      let Some(recording) = marker.to_str().and_then(|m| m.strip_suffix(".recovered")) else {
+        continue;
+      };
+      if !Path::new(recording).exists() {
+        let _ = std::fs::remove_file(&marker);
+      }
+    }
+  }
+  files
+    .into_iter()
+    .filter(|path| is_pending(path))
+    .filter_map(|path| match hound::WavReader::open(&path) {
+      Ok(reader) => {
+        let sample_rate = reader.spec().sample_rate;
+        Some(RecoveredRecording {
+          path: path.to_string_lossy().to_string(),
+          sample_rate,
+          duration_ms: reader.duration() as u64 * 1000 / sample_rate.max(1) as u64,
+        })
+      }
+      Err(e) => {
+        eprintln!("Recovery: dropping {}: {}", path.to_string_lossy(), e);
+        resolve(&path);
+        None
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::{self, TempDir};
+
+  fn write_wav(dir: &TempDir, name: &str, samples: usize) -> PathBuf {
+    let audio: Vec<f32> = (0..samples).map(|i| (i % 100) as f32 / 1000.0).collect();
+    test_util::write_wav(dir.join(name), test_util::pcm_spec(1, 16000, 16), &audio)
+  }
+
+  /// A recording cut short by a crash: the header still says what the last
+  /// checkpoint (a third of the way into `samples`) wrote, but all of
+  /// `samples` and as many again reached the file, plus half a sample.
+  fn write_crashed_wav(dir: &TempDir, name: &str, samples: usize) -> PathBuf {
+    let checkpoint = samples / 3 + 1;
+    let path = write_wav(dir, name, checkpoint);
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    for i in checkpoint..samples * 2 {
+      file.write_all(&(i as i16).to_le_bytes()).unwrap();
+    }
+    // half a sample, as if the process died mid-write
+    file.write_all(&[0x7f]).unwrap();
+    path
+  }
+
+  #[test]
+  fn finalized_file_is_left_alone() {
+    let dir = TempDir::new("recovery-ok");
+    let path = write_wav(&dir, "dictation-ok.wav", 16000);
+    let before = std::fs::read(&path).unwrap();
+    assert!(repair_wav_header(&path).unwrap().is_none());
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+  }
+
+  #[test]
+  fn repairs_unfinalized_file() {
+    let dir = TempDir::new("recovery-crash");
+    let path = write_crashed_wav(&dir, "dictation-crash.wav", 16000);
+    let recovered = repair_wav_header(&path).unwrap().expect("should repair");
+    // everything that reached the file: twice the 16000 samples
+    assert_eq!(recovered.duration_ms, 2000);
+    let reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.len(), 32000);
+    assert!(repair_wav_header(&path).unwrap().is_none());
+  }
+
+  #[test]
+  fn recovered_recordings_stay_pending_until_resolved() {
+    // its own directory: pending() clears stray markers in the one it is given
+    let dir = TempDir::new("recovery-pending");
+    let path = write_crashed_wav(&dir, "dictation-pending.wav", 16000);
+    assert_eq!(recover([path.clone()]), 1);
+    assert!(is_pending(&path));
+
+    // as on the next start: the header is fine now, the marker still says pending
+    assert_eq!(recover([path.clone()]), 0);
+    let listed = pending(dir.path(), [path.clone()]);
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].duration_ms, 2000);
+
+    resolve(&path);
+    assert!(!is_pending(&path));
+    assert!(pending(dir.path(), [path.clone()]).is_empty());
+  }
+}
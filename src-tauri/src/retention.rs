@@ -0,0 +1,251 @@
+// src-tauri/src/retention.rs
+//
+// Retention for the dictation-*.wav files recordings leave in the app cache
+// dir: delete after transcription, keep the last N, keep for N days, or purge
+// everything on demand.
+use std::{
+  path::{Path, PathBuf},
+  time::{Duration, SystemTime},
+};
+use tauri::Manager;
+
+use crate::{recovery, AppSettings};
+
+/// Directory recordings are written to (created if missing).
+pub fn recordings_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  let cache_dir = app
+    .path()
+    .app_cache_dir()
+    .map_err(|e| format!("cache dir: {e}"))?;
+  std::fs::create_dir_all(&cache_dir).map_err(|e| format!("mkdir: {e}"))?;
+  Ok(cache_dir)
+}
+
+/// True for files this app created as recordings; nothing else in the cache dir is touched.
+pub fn is_recording_file(path: &Path) -> bool {
+  path
+    .file_name()
+    .and_then(|n| n.to_str())
+    .map(|n| n.starts_with("dictation-") && n.ends_with(".wav"))
+    .unwrap_or(false)
+}
+
+/// Work file `index` of `recording` ("segment" or "chunk" `prefix`). Never a
+/// dictation-*.wav, so retention never mistakes it for a recording.
+pub fn work_file_path(recording: &Path, prefix: &str, index: usize) -> PathBuf {
+  let stem = recording
+    .file_stem()
+    .map(|s| s.to_string_lossy().to_string())
+    .unwrap_or_default();
+  recording.with_file_name(format!("{prefix}-{stem}-{index:03}.wav"))
+}
+
+/// Recordings in `dir`, newest first.
+pub fn list_recordings(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+  let mut files: Vec<(PathBuf, SystemTime)> = match std::fs::read_dir(dir) {
+    Ok(entries) => entries
+      .filter_map(|e| e.ok())
+      .map(|e| e.path())
+      .filter(|p| is_recording_file(p))
+      .filter_map(|p| {
+        let modified = std::fs::metadata(&p).and_then(|m| m.modified()).ok()?;
+        Some((p, modified))
+      })
+      .collect(),
+    Err(e) => {
+      eprintln!("Retention: failed to read {}: {}", dir.to_string_lossy(), e);
+      Vec::new()
+    }
+  };
+  files.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+  files
+}
+
+fn remove(path: &Path) -> bool {
+  match std::fs::remove_file(path) {
+    Ok(_) => true,
+    Err(e) => {
+      eprintln!("Retention: failed to delete {}: {}", path.to_string_lossy(), e);
+      false
+    }
+  }
+}
+
+/// Apply keep-last-N and keep-N-days to the recordings in `dir`. Recovered
+/// recordings still waiting to be transcribed or discarded are neither deleted
+/// nor counted. Must not run while a recording is being written. Returns how
+/// many files were deleted.
+pub fn sweep(dir: &Path, settings: &AppSettings) -> usize {
+  let max_age = (settings.keep_recordings_days > 0)
+    .then(|| Duration::from_secs(settings.keep_recordings_days as u64 * 24 * 60 * 60));
+  let now = SystemTime::now();
+
+  let mut deleted = 0;
+  let recordings = list_recordings(dir)
+    .into_iter()
+    .filter(|(path, _)| !recovery::is_pending(path));
+  for (index, (path, modified)) in recordings.enumerate() {
+    let over_count = settings.keep_recordings > 0 && index >= settings.keep_recordings as usize;
+    let too_old = max_age
+      .map(|max| now.duration_since(modified).unwrap_or_default() > max)
+      .unwrap_or(false);
+    if (over_count || too_old) && remove(&path) {
+      deleted += 1;
+    }
+  }
+  if deleted > 0 {
+    eprintln!("Retention: deleted {} old recordings", deleted);
+  }
+  deleted
+}
+
+/// Delete every recording in `dir` except `keep` (the one being written),
+/// recovered ones included.
+pub fn purge(dir: &Path, keep: Option<&Path>) -> usize {
+  let mut deleted = 0;
+  for (path, _) in list_recordings(dir) {
+    if Some(path.as_path()) == keep || !remove(&path) {
+      continue;
+    }
+    recovery::resolve(&path);
+    deleted += 1;
+  }
+  deleted
+}
+
+/// Delete streaming segments and split chunks left behind when the app quit
+/// mid-upload. Only safe at startup, before anything is being transcribed.
+pub fn remove_stale_segments(dir: &Path) -> usize {
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return 0;
+  };
+  entries
+    .filter_map(|e| e.ok())
+    .map(|e| e.path())
+    .filter(|p| {
+      p.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| (n.starts_with("segment-") || n.starts_with("chunk-")) && n.ends_with(".wav"))
+        .unwrap_or(false)
+    })
+    .filter(|p| remove(p))
+    .count()
+}
+
+/// Called after a successful transcription of `path`.
+pub fn after_transcription(app: &tauri::AppHandle, settings: &AppSettings, path: &Path) {
+  if !settings.delete_after_transcription {
+    return;
+  }
+  // only our own recordings; never a file the user pointed us at
+  let in_cache = recordings_dir(app)
+    .map(|dir| path.parent() == Some(dir.as_path()))
+    .unwrap_or(false);
+  if in_cache && is_recording_file(path) && remove(path) {
+    eprintln!("Retention: deleted transcribed recording {}", path.to_string_lossy());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::TempDir;
+
+  /// Create `name` in `dir`, last modified `age` ago.
+  fn file(dir: &TempDir, name: &str, age: Duration) -> PathBuf {
+    let path = dir.join(name);
+    let file = std::fs::File::create(&path).unwrap();
+    file.set_modified(SystemTime::now() - age).unwrap();
+    path
+  }
+
+  fn names(dir: &TempDir) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir.path())
+      .unwrap()
+      .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+      .collect();
+    names.sort();
+    names
+  }
+
+  const HOUR: Duration = Duration::from_secs(60 * 60);
+
+  fn settings(keep_recordings: u32, keep_recordings_days: u32) -> AppSettings {
+    AppSettings {
+      keep_recordings,
+      keep_recordings_days,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn keeps_the_newest_recordings() {
+    let dir = TempDir::new("retention-count");
+    for (i, name) in ["c", "a", "d", "b"].iter().enumerate() {
+      file(&dir, &format!("dictation-{name}.wav"), HOUR * (i as u32 + 1));
+    }
+    assert_eq!(list_recordings(dir.path()).len(), 4);
+    assert_eq!(sweep(dir.path(), &settings(2, 0)), 2);
+    assert_eq!(names(&dir), ["dictation-a.wav", "dictation-c.wav"]);
+  }
+
+  #[test]
+  fn deletes_recordings_past_the_age_cutoff() {
+    let dir = TempDir::new("retention-age");
+    file(&dir, "dictation-new.wav", HOUR);
+    file(&dir, "dictation-old.wav", HOUR * 24 * 3);
+    assert_eq!(sweep(dir.path(), &settings(0, 2)), 1);
+    assert_eq!(names(&dir), ["dictation-new.wav"]);
+    // neither limit set: nothing goes
+    assert_eq!(sweep(dir.path(), &settings(0, 0)), 0);
+  }
+
+  #[test]
+  fn recovered_recordings_are_neither_deleted_nor_counted() {
+    let dir = TempDir::new("retention-recovered");
+    let recovered = file(&dir, "dictation-recovered.wav", HOUR * 24 * 3);
+    // as recovery::recover leaves it
+    file(&dir, "dictation-recovered.wav.recovered", HOUR * 24 * 3);
+    file(&dir, "dictation-new.wav", HOUR * 2);
+    file(&dir, "dictation-old.wav", HOUR * 3);
+    assert!(recovery::is_pending(&recovered));
+    assert_eq!(sweep(dir.path(), &settings(1, 2)), 1);
+    assert_eq!(
+      names(&dir),
+      ["dictation-new.wav", "dictation-recovered.wav", "dictation-recovered.wav.recovered"]
+    );
+  }
+
+  #[test]
+  fn purge_spares_the_recording_being_written() {
+    let dir = TempDir::new("retention-purge");
+    let current = file(&dir, "dictation-current.wav", Duration::ZERO);
+    file(&dir, "dictation-done.wav", HOUR);
+    let recovered = file(&dir, "dictation-recovered.wav", HOUR * 2);
+    file(&dir, "dictation-recovered.wav.recovered", HOUR * 2);
+    assert_eq!(purge(dir.path(), Some(&current)), 2);
+    // the marker goes with its recording
+    assert!(!recovery::is_pending(&recovered));
+    assert_eq!(names(&dir), ["dictation-current.wav"]);
+    assert_eq!(purge(dir.path(), None), 1);
+    assert!(names(&dir).is_empty());
+  }
+
+  #[test]
+  fn only_recordings_are_touched() {
+    let dir = TempDir::new("retention-others");
+    let others = [
+      "chunk-dictation-a-000.wav",
+      "dictation-a.txt",
+      "notes.wav",
+      "segment-dictation-a-000.wav",
+    ];
+    for name in others {
+      file(&dir, name, HOUR * 24 * 30);
+    }
+    file(&dir, "dictation-a.wav", HOUR * 24 * 30);
+    assert_eq!(sweep(dir.path(), &settings(0, 1)), 1);
+    assert_eq!(purge(dir.path(), None), 0);
+    assert_eq!(names(&dir), others);
+  }
+}
@@ -0,0 +1,110 @@
+// src-tauri/src/segments.rs
+//
+// Incremental transcription. With streaming on, the writer closes a segment
+// file at each pause (or at a maximum length) while the recording goes on and
+// hands it here. Each segment is transcribed in the background, so when the
+// recording stops only the tail is still outstanding. Results are stitched in
+// segment order.
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+};
+use tauri::{async_runtime::JoinHandle, Emitter};
+
+use crate::{retention, TranscribeError, TranscribeResponse};
+
+/// Payload of the `transcription-segment` event.
+#[derive(Serialize, Clone)]
+pub struct SegmentTranscribed {
+  pub recording: String,
+  pub index: usize,
+  pub text: String,
+}
+
+type SegmentTask = JoinHandle<Result<String, TranscribeError>>;
+
+/// Segment transcriptions in flight, keyed by the recording they belong to.
+#[derive(Default)]
+pub struct SegmentPipeline {
+  recordings: Mutex<HashMap<PathBuf, Vec<(usize, SegmentTask)>>>,
+}
+
+/// File for segment `index` of `recording`.
+pub fn segment_path(recording: &Path, index: usize) -> PathBuf {
+  retention::work_file_path(recording, "segment", index)
+}
+
+impl SegmentPipeline {
+  /// Start transcribing a finished segment file. Called from the writer thread.
+  pub fn submit(&self, app: &tauri::AppHandle, recording: &Path, index: usize, segment: PathBuf) {
+    let app = app.clone();
+    let recording_name = recording.to_string_lossy().to_string();
+    let task = tauri::async_runtime::spawn(async move {
+      let result = crate::transcribe_with_settings(app.clone(), segment.to_string_lossy().to_string()).await;
+      // segments are scratch copies; the full recording is kept on its own
+      let _ = std::fs::remove_file(&segment);
+      let text = match result {
+        Ok(response) => response.text.trim().to_string(),
+        // a segment that is all pause is expected, not a failure
+        Err(TranscribeError::NothingHeard(_)) => String::new(),
+        Err(e) => return Err(e),
+      };
+      eprintln!("Segment {} of {}: {} chars", index, recording_name, text.len());
+      let _ = app.emit_to(
+        "panel",
+        "transcription-segment",
+        SegmentTranscribed {
+          recording: recording_name,
+          index,
+          text: text.clone(),
+        },
+      );
+      Ok(text)
+    });
+    self
+      .recordings
+      .lock()
+      .entry(recording.to_path_buf())
+      .or_default()
+      .push((index, task));
+  }
+
+  /// Wait for every segment of `recording` and join their text in order.
+  pub async fn finish(&self, recording: &Path) -> Result<TranscribeResponse, TranscribeError> {
+    let mut tasks = self.recordings.lock().remove(recording).unwrap_or_default();
+    if tasks.is_empty() {
+      return Err("No segments were recorded".into());
+    }
+    tasks.sort_by_key(|(index, _)| *index);
+
+    let mut parts = Vec::new();
+    for (index, task) in tasks {
+      let text = task
+        .await
+        .map_err(|e| format!("segment {}: {e}", index + 1))?
+        .map_err(|e| match e {
+          TranscribeError::Failed(message) => TranscribeError::Failed(format!("segment {}: {message}", index + 1)),
+          other => other,
+        })?;
+      if !text.is_empty() {
+        parts.push(text);
+      }
+    }
+    if parts.is_empty() {
+      return Err(TranscribeError::NothingHeard("Nothing was heard in any segment".into()));
+    }
+    Ok(TranscribeResponse { text: parts.join(" ") })
+  }
+
+  /// Forget a cancelled recording: stop its uploads and delete its segment files.
+  pub fn discard(&self, recording: &Path) {
+    if let Some(tasks) = self.recordings.lock().remove(recording) {
+      for (index, task) in tasks {
+        task.abort();
+        let _ = std::fs::remove_file(segment_path(recording, index));
+      }
+    }
+  }
+}
@@ -0,0 +1,78 @@
+// src-tauri/src/test_util.rs
+//
+// Fixtures shared by the unit tests: scratch directories that remove
+// themselves, and WAV files to put in them.
+use std::{
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A fresh directory under the system temp dir, deleted with everything in it
+/// when dropped, so a failed assertion leaves nothing behind and no test sees
+/// another's files.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+  pub fn new(name: &str) -> Self {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+      "hotkey-type-{}-{}-{}",
+      name,
+      std::process::id(),
+      NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    TempDir(dir)
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.0
+  }
+
+  pub fn join(&self, name: &str) -> PathBuf {
+    self.0.join(name)
+  }
+}
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.0);
+  }
+}
+
+/// Integer PCM layout.
+pub fn pcm_spec(channels: u16, sample_rate: u32, bits_per_sample: u16) -> hound::WavSpec {
+  hound::WavSpec {
+    channels,
+    sample_rate,
+    bits_per_sample,
+    sample_format: hound::SampleFormat::Int,
+  }
+}
+
+/// Segments of (amplitude, ms) of a 220 Hz tone; amplitude 0 is silence.
+pub fn tone(rate: u32, segments: &[(f32, u32)]) -> Vec<f32> {
+  let mut out = Vec::new();
+  for &(amp, ms) in segments {
+    for _ in 0..(rate * ms / 1000) {
+      let t = out.len() as f32 / rate as f32;
+      out.push(amp * (2.0 * std::f32::consts::PI * 220.0 * t).sin());
+    }
+  }
+  out
+}
+
+/// Write normalised, interleaved `samples` to `path` in `spec`'s layout.
+pub fn write_wav(path: PathBuf, spec: hound::WavSpec, samples: &[f32]) -> PathBuf {
+  let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+  let scale = ((1i64 << (spec.bits_per_sample - 1)) - 1) as f32;
+  for &s in samples {
+    match spec.sample_format {
+      hound::SampleFormat::Float => writer.write_sample(s).unwrap(),
+      hound::SampleFormat::Int => writer.write_sample((s.clamp(-1.0, 1.0) * scale).round() as i32).unwrap(),
+    }
+  }
+  writer.finalize().unwrap();
+  path
+}
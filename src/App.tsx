@@ -5,15 +5,63 @@ import { getCurrentWindow } from "@tauri-apps/api/window";
 import Settings from "./Settings";
 import "./App.css";
 
-type Phase = "IDLE" | "RECORDING" | "TRANSCRIBING" | "PASTING" | "DONE" | "ERROR";
+type Phase = "IDLE" | "RECORDING" | "PAUSED" | "TRANSCRIBING" | "PASTING" | "DONE" | "ERROR";
+
+interface RecordingLevel {
+  rms: number;
+  peak: number;
+  rms_db: number;
+  peak_db: number;
+}
+
+interface RecordingStopped {
+  path: string;
+  sample_rate: number;
+  duration_ms: number;
+  samples: number;
+  peak: number;
+  rms: number;
+  peak_db: number;
+  rms_db: number;
+  clipped_samples: number;
+  silence_ratio: number;
+  overruns: number;
+  dropped_ms: number;
+  segments: number;
+}
+
+interface RecordingError {
+  message: string;
+  // partial recording finalized after the input device failed
+  recording: RecordingStopped | null;
+  // false: the stream glitched but the recording goes on
+  fatal: boolean;
+}
+
+interface DeviceChoice {
+  device: string;
+  reason: string;
+  preference: number | null;
+  fallback: boolean;
+}
+
+// Error shape returned by openai_transcribe / google_transcribe
+interface TranscribeError {
+  kind: "nothing_heard" | "failed";
+  message: string;
+}
+
+const isTranscribeError = (e: unknown): e is TranscribeError =>
+  typeof e === "object" && e !== null && "kind" in e && "message" in e;
 
 interface Settings {
   provider: string;
   openai_api_key: string;
   google_api_key: string;
   google_language: string;
-  input_device_name: string;
+  input_devices: string[];
   panel_visible?: boolean;
+  pause_hotkey?: string;
 }
 
 export default function App() {
@@ -24,9 +72,14 @@ export default function App() {
   const [openaiApiKey, setOpenaiApiKey] = useState<string>("");
   const [googleApiKey, setGoogleApiKey] = useState<string>("");
   const [googleLanguage, setGoogleLanguage] = useState<string>("en-US");
+  // 0..1, derived from the backend's RMS level in dBFS
+  const [inputLevel, setInputLevel] = useState<number>(0);
+  // mic stream held open while idle for pre-roll (privacy indicator)
+  const [warmMic, setWarmMic] = useState<boolean>(false);
   const lastHandledRef = useRef<number>(0);
   const recordingRef = useRef<boolean>(false);
   const lastPathRef = useRef<string | null>(null);
+  const pauseHotkeyRef = useRef<string>("Ctrl+Shift+P");
 
   // Get window label on mount
   useEffect(() => {
@@ -85,24 +138,219 @@ export default function App() {
       setOpenaiApiKey(settings.openai_api_key || "");
       setGoogleApiKey(settings.google_api_key || "");
       setGoogleLanguage(settings.google_language || "en-US");
+      pauseHotkeyRef.current = settings.pause_hotkey ?? "Ctrl+Shift+P";
     } catch (e) {
       console.error("Failed to load settings:", e);
     }
   };
 
-  // Only set up dictation toggle listener for panel window
+  // Warm-mic privacy indicator (panel only)
   useEffect(() => {
     if (windowLabel !== "panel") {
       return;
     }
 
-    console.log("Setting up event listener for 'dictation-toggle'...");
+    invoke<{ warm_mic: boolean }>("recording_status")
+      .then((status) => setWarmMic(status.warm_mic))
+      .catch((e) => console.error("Failed to get recorder status:", e));
+
+    const unlisten = listen<boolean>("warm-mic", (event) => {
+      setWarmMic(event.payload);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [windowLabel]);
 
-    let unlistenFn: (() => void) | null = null;
+  // Live input meter while recording (panel only)
+  useEffect(() => {
+    if (windowLabel !== "panel") {
+      return;
+    }
+    if (phase !== "RECORDING") {
+      setInputLevel(0);
+      return;
+    }
+
+    const unlisten = listen<RecordingLevel>("recording-level", (event) => {
+      // map -60 dBFS..0 dBFS onto 0..1
+      const db = Math.max(-60, Math.min(0, event.payload.rms_db));
+      setInputLevel((db + 60) / 60);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [windowLabel, phase]);
+
+  // Only set up dictation listeners for panel window
+  useEffect(() => {
+    if (windowLabel !== "panel") {
+      return;
+    }
+
+    console.log("Setting up event listeners for dictation...");
+
+    const unlistenFns: (() => void)[] = [];
     let cancelled = false;
 
-    listen("dictation-toggle", async (event) => {
-      console.log("✓ Toggle event received:", event);
+    const showError = (e: unknown) => {
+      recordingRef.current = false;
+
+      // Pre-flight rejected the recording (too short or too quiet) or no speech was found: not a failure
+      if (isTranscribeError(e) && e.kind === "nothing_heard") {
+        console.log("Nothing was heard:", e.message);
+        setPhase("DONE");
+        setMessage("Nothing was heard");
+        setTimeout(() => {
+          setPhase("IDLE");
+          setMessage("");
+        }, 2000);
+        return;
+      }
+
+      setPhase("ERROR");
+      const errorMsg = isTranscribeError(e)
+        ? e.message
+        : e instanceof Error
+          ? e.message
+          : String(e);
+      setMessage(`Error: ${errorMsg}`);
+      console.error("Dictation error:", e);
+
+      // Auto return to IDLE after 3 seconds on error
+      setTimeout(() => {
+        setPhase("IDLE");
+        setMessage("");
+      }, 3000);
+    };
+
+    // Shared by the manual toggle and backend auto-stop (voice activity detection)
+    const transcribeAndPaste = async (stopped: RecordingStopped) => {
+      recordingRef.current = false;
+      console.log("Recording stopped:", stopped);
+      if (stopped.overruns > 0) {
+        console.warn(`Audio overruns: ${stopped.overruns} (${stopped.dropped_ms} ms of audio dropped)`);
+      }
+      setPhase("TRANSCRIBING");
+      setMessage("Transcribing...");
+
+      let providerToUse = provider;
+      let openaiKeyToUse = openaiApiKey?.trim() ?? "";
+      let googleKeyToUse = googleApiKey?.trim() ?? "";
+      let googleLanguageToUse = googleLanguage?.trim() ?? "";
+      const needsRefresh =
+        !providerToUse ||
+        (providerToUse === "openai" && !openaiKeyToUse) ||
+        (providerToUse === "google" && !googleKeyToUse);
+
+      if (needsRefresh) {
+        // Reload settings in case the key was just saved
+        const latest = await invoke<Settings>("get_settings");
+        providerToUse = latest.provider === "google" ? "google" : "openai";
+        openaiKeyToUse = (latest.openai_api_key || "").trim();
+        googleKeyToUse = (latest.google_api_key || "").trim();
+        googleLanguageToUse = (latest.google_language || "").trim();
+        setProvider(providerToUse);
+        setOpenaiApiKey(openaiKeyToUse);
+        setGoogleApiKey(googleKeyToUse);
+        setGoogleLanguage(googleLanguageToUse || "en-US");
+      }
+      if (providerToUse === "openai" && !openaiKeyToUse) {
+        throw new Error("Please set your OpenAI API key in Settings");
+      }
+      if (providerToUse === "google" && !googleKeyToUse) {
+        throw new Error("Please set your Google API key in Settings");
+      }
+      if (providerToUse === "google" && !googleLanguageToUse) {
+        googleLanguageToUse = "en-US";
+      }
+
+      // Transcribe; a streamed recording only waits for its last segments
+      const { text } =
+        stopped.segments > 0
+          ? await invoke<{ text: string }>("finish_segment_transcription", { path: stopped.path })
+          : providerToUse === "google"
+          ? await invoke<{ text: string }>("google_transcribe", {
+              audioPath: stopped.path,
+              apiKey: googleKeyToUse,
+              language: googleLanguageToUse,
+              model: "default",
+              enableAutomaticPunctuation: true,
+            })
+          : await invoke<{ text: string }>("openai_transcribe", {
+              audioPath: stopped.path,
+              apiKey: openaiKeyToUse,
+              model: "whisper-1",
+            });
+
+      console.log("Transcribed text:", text);
+
+      if (!text || text.trim().length === 0) {
+        setPhase("ERROR");
+        setMessage("No text was transcribed from the audio");
+        return;
+      }
+
+      // Paste
+      setPhase("PASTING");
+      setMessage("Pasting...");
+
+      const pasted = await invoke<boolean>("paste_text", { text });
+
+      if (pasted) {
+        setPhase("DONE");
+        setMessage(`Pasted: "${text}"`);
+      } else {
+        setPhase("DONE");
+        setMessage(`Copied to clipboard (press ⌘V): "${text}"`);
+      }
+
+      // Auto return to IDLE after 2 seconds
+      setTimeout(() => {
+        setPhase("IDLE");
+        setMessage("");
+      }, 2000);
+    };
+
+    const register = <T,>(name: string, handler: (payload: T) => Promise<void>) => {
+      listen<T>(name, (event) => handler(event.payload))
+        .then((unlisten) => {
+          if (cancelled) {
+            unlisten();
+            return;
+          }
+          unlistenFns.push(unlisten);
+          console.log(`✓ Listener for '${name}' registered successfully`);
+        })
+        .catch((err) => {
+          console.error(`✗ Failed to register listener for '${name}':`, err);
+        });
+    };
+
+    const startRecording = async () => {
+      recordingRef.current = true;
+      setPhase("RECORDING");
+      setMessage("Recording...");
+
+      const path = await invoke<string>("start_recording");
+      lastPathRef.current = path;
+      console.log("Recording started, path:", path);
+    };
+
+    const stopAndTranscribe = async () => {
+      recordingRef.current = false;
+      setPhase("TRANSCRIBING");
+      setMessage("Transcribing...");
+
+      const stopped = await invoke<RecordingStopped>("stop_recording");
+      console.log("Recording stopped:", stopped);
+      await transcribeAndPaste(stopped);
+    };
+
+    register<null>("dictation-toggle", async () => {
+      console.log("✓ Toggle event received");
 
       const now = Date.now();
       if (now - lastHandledRef.current < 150) {
@@ -113,131 +361,120 @@ export default function App() {
 
       try {
         if (!recordingRef.current) {
-          // Start recording
-          recordingRef.current = true;
-          setPhase("RECORDING");
-          setMessage("Recording...");
-
-          const path = await invoke<string>("start_recording");
-          lastPathRef.current = path;
-          console.log("Recording started, path:", path);
+          await startRecording();
         } else {
-          // Stop recording
-          recordingRef.current = false;
-          setPhase("TRANSCRIBING");
-          setMessage("Transcribing...");
-
-          const stopped = await invoke<{ path: string; sample_rate: number; duration_ms: number }>("stop_recording");
-          console.log("Recording stopped:", stopped);
-
-          let providerToUse = provider;
-          let openaiKeyToUse = openaiApiKey?.trim() ?? "";
-          let googleKeyToUse = googleApiKey?.trim() ?? "";
-          let googleLanguageToUse = googleLanguage?.trim() ?? "";
-          const needsRefresh =
-            !providerToUse ||
-            (providerToUse === "openai" && !openaiKeyToUse) ||
-            (providerToUse === "google" && !googleKeyToUse);
-
-          if (needsRefresh) {
-            // Reload settings in case the key was just saved
-            const latest = await invoke<Settings>("get_settings");
-            providerToUse = latest.provider === "google" ? "google" : "openai";
-            openaiKeyToUse = (latest.openai_api_key || "").trim();
-            googleKeyToUse = (latest.google_api_key || "").trim();
-            googleLanguageToUse = (latest.google_language || "").trim();
-            setProvider(providerToUse);
-            setOpenaiApiKey(openaiKeyToUse);
-            setGoogleApiKey(googleKeyToUse);
-            setGoogleLanguage(googleLanguageToUse || "en-US");
-          }
-          if (providerToUse === "openai" && !openaiKeyToUse) {
-            throw new Error("Please set your OpenAI API key in Settings");
-          }
-          if (providerToUse === "google" && !googleKeyToUse) {
-            throw new Error("Please set your Google API key in Settings");
-          }
-          if (providerToUse === "google" && !googleLanguageToUse) {
-            googleLanguageToUse = "en-US";
-          }
+          await stopAndTranscribe();
+        }
+      } catch (e) {
+        showError(e);
+      }
+    });
 
-          // Transcribe
-          const { text } =
-            providerToUse === "google"
-              ? await invoke<{ text: string }>("google_transcribe", {
-                  audioPath: stopped.path,
-                  apiKey: googleKeyToUse,
-                  language: googleLanguageToUse,
-                  model: "default",
-                  enableAutomaticPunctuation: true,
-                })
-              : await invoke<{ text: string }>("openai_transcribe", {
-                  audioPath: stopped.path,
-                  apiKey: openaiKeyToUse,
-                  model: "whisper-1",
-                });
-
-          console.log("Transcribed text:", text);
-
-          if (!text || text.trim().length === 0) {
-            setPhase("ERROR");
-            setMessage("No text was transcribed from the audio");
-            return;
-          }
+    // Push-to-talk: hotkey pressed
+    register<null>("dictation-start", async () => {
+      if (recordingRef.current) return;
+      try {
+        await startRecording();
+      } catch (e) {
+        showError(e);
+      }
+    });
 
-          // Paste
-          setPhase("PASTING");
-          setMessage("Pasting...");
+    // Push-to-talk: hotkey released after the minimum hold
+    register<null>("dictation-stop", async () => {
+      if (!recordingRef.current) return;
+      try {
+        await stopAndTranscribe();
+      } catch (e) {
+        showError(e);
+      }
+    });
 
-          const pasted = await invoke<boolean>("paste_text", { text });
+    // Push-to-talk: released too quickly, treat as an accidental tap
+    register<null>("dictation-discard", async () => {
+      if (!recordingRef.current) return;
+      try {
+        await invoke("cancel_recording");
+        setMessage("Hold the hotkey to talk");
+      } catch (e) {
+        showError(e);
+      }
+    });
 
-          if (pasted) {
-            setPhase("DONE");
-            setMessage(`Pasted: "${text}"`);
-          } else {
-            setPhase("DONE");
-            setMessage(`Copied to clipboard (press ⌘V): "${text}"`);
-          }
+    // Backend finalized the recording by itself (silence after speech)
+    register<RecordingStopped>("recording-stopped", async (stopped) => {
+      console.log("Recording auto-stopped:", stopped);
+      try {
+        await transcribeAndPaste(stopped);
+      } catch (e) {
+        showError(e);
+      }
+    });
 
-          // Auto return to IDLE after 2 seconds
-          setTimeout(() => {
-            setPhase("IDLE");
-            setMessage("");
-          }, 2000);
+    register<RecordingError>("recording-error", async (error) => {
+      if (!error.fatal) {
+        console.warn("Audio stream error:", error.message);
+        if (recordingRef.current) {
+          setMessage(`Warning: ${error.message}`);
         }
+        return;
+      }
+      if (!error.recording) {
+        showError(error.message);
+        return;
+      }
+      // Device went away mid-recording: the partial file is still usable
+      console.warn("Recording interrupted, transcribing partial audio:", error.message);
+      try {
+        await transcribeAndPaste(error.recording);
       } catch (e) {
-        recordingRef.current = false;
-        setPhase("ERROR");
-        const errorMsg = e instanceof Error ? e.message : String(e);
-        setMessage(`Error: ${errorMsg}`);
-        console.error("Dictation error:", e);
+        showError(e);
+      }
+    });
 
-        // Auto return to IDLE after 3 seconds on error
-        setTimeout(() => {
-          setPhase("IDLE");
-          setMessage("");
-        }, 3000);
+    // Cancel hotkey (Escape by default) or cancel_recording: the file is already deleted
+    register<null>("dictation-cancelled", async () => {
+      console.log("Recording cancelled");
+      recordingRef.current = false;
+      setPhase("IDLE");
+      setMessage("Cancelled");
+      setTimeout(() => setMessage(""), 1500);
+    });
+
+    // Pause hotkey (Ctrl+Shift+P by default) or pause_recording/resume_recording
+    register<boolean>("recording-paused", async (paused) => {
+      if (!recordingRef.current) return;
+      const hotkey = pauseHotkeyRef.current.trim();
+      setPhase(paused ? "PAUSED" : "RECORDING");
+      setMessage(paused ? (hotkey ? `Paused (${hotkey} to resume)` : "Paused") : "Recording...");
+    });
+
+    // Which microphone this recording uses; only worth showing when no preference matched
+    register<DeviceChoice>("input-device", async (choice) => {
+      console.log(`Input device: ${choice.device} (${choice.reason})`);
+      if (choice.fallback) {
+        setMessage(`Using ${choice.device}`);
       }
-    })
-      .then((unlisten) => {
-        if (cancelled) {
-          unlisten();
-          return;
-        }
-        unlistenFn = unlisten;
-        console.log("✓ Listener for 'dictation-toggle' registered successfully");
-      })
-      .catch((err) => {
-        console.error("✗ Failed to register listener for 'dictation-toggle':", err);
-      });
+    });
+
+    // Streaming transcription: a segment finished uploading while recording continues
+    register<{ recording: string; index: number; text: string }>("transcription-segment", async (segment) => {
+      console.log(`Segment ${segment.index + 1} transcribed:`, segment.text);
+    });
+
+    // A recording over the provider's limits goes up in chunks; they finish out of order
+    register<{ recording: string; index: number; count: number; completed: number }>(
+      "transcription-chunk",
+      async (chunk) => {
+        console.log(`Chunk ${chunk.index + 1} of ${chunk.count} transcribed`);
+        setMessage(`Transcribing... (${chunk.completed}/${chunk.count})`);
+      }
+    );
 
     return () => {
-      console.log("Cleaning up event listener...");
+      console.log("Cleaning up event listeners...");
       cancelled = true;
-      if (unlistenFn) {
-        unlistenFn();
-        console.log("Event listener unregistered");
-      }
+      unlistenFns.forEach((fn) => fn());
     };
   }, [windowLabel]);
 
@@ -267,6 +504,8 @@ export default function App() {
       switch (phase) {
         case "RECORDING":
           return "#ff4444";
+        case "PAUSED":
+          return "#999";
         case "ERROR":
           return "#ff8800";
         case "TRANSCRIBING":
@@ -326,6 +565,18 @@ export default function App() {
             pointerEvents: "none",
           }}
         >
+          {phase === "RECORDING" && (
+            <div
+              style={{
+                position: "absolute",
+                width: 20 + inputLevel * 40,
+                height: 20 + inputLevel * 40,
+                borderRadius: "50%",
+                backgroundColor: "rgba(255, 68, 68, 0.3)",
+                transition: "width 0.05s linear, height 0.05s linear",
+              }}
+            />
+          )}
           {phase === "RECORDING" && (
             <div
               style={{
@@ -340,9 +591,31 @@ export default function App() {
           {phase === "IDLE" && (
             <div style={{ fontSize: 20, opacity: 0.6 }}>🎤</div>
           )}
+          {warmMic && phase !== "RECORDING" && (
+            <div
+              title="Microphone is open (warm mic)"
+              style={{
+                position: "absolute",
+                top: 6,
+                right: 6,
+                width: 8,
+                height: 8,
+                borderRadius: "50%",
+                backgroundColor: "#ffaa00",
+              }}
+            />
+          )}
           {phase !== "IDLE" && phase !== "RECORDING" && (
             <div style={{ fontSize: 12, color: getStatusColor(), fontWeight: "bold" }}>
-              {phase === "TRANSCRIBING" ? "⏳" : phase === "PASTING" ? "📋" : phase === "DONE" ? "✓" : "⚠"}
+              {phase === "PAUSED"
+                ? "⏸"
+                : phase === "TRANSCRIBING"
+                  ? "⏳"
+                  : phase === "PASTING"
+                    ? "📋"
+                    : phase === "DONE"
+                      ? "✓"
+                      : "⚠"}
             </div>
           )}
           {message && (
@@ -1,34 +1,166 @@
 import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { getCurrentWebview } from "@tauri-apps/api/webview";
 
 interface Settings {
   provider: string;
   openai_api_key: string;
   google_api_key: string;
   google_language: string;
-  input_device_name: string;
+  audio_host: string;
+  input_devices: string[];
+  input_configs: Record<string, DeviceConfig>;
+  input_channel: number | null;
+  target_sample_rate: number;
+  upload_format: string;
+  system_audio: string;
+  system_audio_device: string;
+  streaming_transcription: boolean;
+  segment_pause_ms: number;
+  segment_max_ms: number;
+  vad_auto_stop: boolean;
+  vad_silence_ms: number;
+  warm_mic: boolean;
+  preroll_ms: number;
+  min_recording_ms: number;
+  min_speech_db: number;
+  hotkey_mode: string;
+  push_to_talk_min_hold_ms: number;
+  cancel_hotkey: string;
+  pause_hotkey: string;
+  delete_after_transcription: boolean;
+  keep_recordings: number;
+  keep_recordings_days: number;
   panel_visible: boolean;
 }
 
+interface DeviceConfig {
+  channels: number;
+  sample_rate: number;
+  sample_format: string;
+}
+
+interface SupportedConfigRange {
+  channels: number;
+  min_sample_rate: number;
+  max_sample_rate: number;
+  sample_format: string;
+}
+
 interface InputDevice {
   name: string;
   is_default: boolean;
+  host: string;
+  default_config: DeviceConfig | null;
+  supported_configs: SupportedConfigRange[];
+  is_loopback: boolean;
+}
+
+interface AudioHost {
+  id: string;
+  is_default: boolean;
+}
+
+interface DeviceTest {
+  device: string;
+  sample_rate: number;
+  duration_ms: number;
+  peak_db: number;
+  rms_db: number;
+  loudest_frame_db: number;
+  clipped_samples: number;
+  silence_ratio: number;
+  wav_base64: string;
+}
+
+interface RecoveredRecording {
+  path: string;
+  sample_rate: number;
+  duration_ms: number;
 }
 
+const errorText = (e: unknown) =>
+  typeof e === "object" && e !== null && "message" in e ? String((e as { message: unknown }).message) : String(e);
+
+const COMMON_SAMPLE_RATES = [8000, 16000, 22050, 24000, 32000, 44100, 48000, 96000];
+
+const configKey = (c: DeviceConfig) => `${c.channels}:${c.sample_rate}:${c.sample_format}`;
+
+const configLabel = (c: DeviceConfig) =>
+  `${c.sample_rate} Hz · ${c.channels} ch · ${c.sample_format}`;
+
+// Concrete configs a device offers: common rates inside each supported range, plus the range ends
+const expandConfigs = (ranges: SupportedConfigRange[]): DeviceConfig[] => {
+  const seen = new Set<string>();
+  const configs: DeviceConfig[] = [];
+  for (const range of ranges) {
+    const rates = [range.min_sample_rate, ...COMMON_SAMPLE_RATES, range.max_sample_rate].filter(
+      (rate) => rate >= range.min_sample_rate && rate <= range.max_sample_rate
+    );
+    for (const sample_rate of rates) {
+      const config = { channels: range.channels, sample_rate, sample_format: range.sample_format };
+      if (!seen.has(configKey(config))) {
+        seen.add(configKey(config));
+        configs.push(config);
+      }
+    }
+  }
+  return configs;
+};
+
 export default function Settings() {
   const [settings, setSettings] = useState<Settings>({
     provider: "openai",
     openai_api_key: "",
     google_api_key: "",
     google_language: "en-US",
-    input_device_name: "",
+    audio_host: "",
+    input_devices: [],
+    input_configs: {},
+    input_channel: null,
+    target_sample_rate: 16000,
+    upload_format: "wav",
+    system_audio: "off",
+    system_audio_device: "",
+    streaming_transcription: false,
+    segment_pause_ms: 700,
+    segment_max_ms: 30000,
+    vad_auto_stop: false,
+    vad_silence_ms: 1500,
+    warm_mic: false,
+    preroll_ms: 500,
+    min_recording_ms: 300,
+    min_speech_db: -50,
+    hotkey_mode: "toggle",
+    push_to_talk_min_hold_ms: 300,
+    cancel_hotkey: "Escape",
+    pause_hotkey: "Ctrl+Shift+P",
+    delete_after_transcription: false,
+    keep_recordings: 0,
+    keep_recordings_days: 0,
     panel_visible: true,
   });
   const [saved, setSaved] = useState(false);
   const [loading, setLoading] = useState(true);
   const [inputDevices, setInputDevices] = useState<InputDevice[]>([]);
+  const [audioHosts, setAudioHosts] = useState<AudioHost[]>([]);
+  // upload formats this build can encode; Opus (and importing Opus files) only with the opus feature
+  const [uploadFormats, setUploadFormats] = useState<string[]>(["wav", "flac"]);
+  const [newPreference, setNewPreference] = useState("");
+  // device whose capture format is being edited; "" = system default device
+  const [formatDeviceName, setFormatDeviceName] = useState("");
+  const [deviceTest, setDeviceTest] = useState<DeviceTest | null>(null);
+  const [testingDevice, setTestingDevice] = useState(false);
+  const [recovered, setRecovered] = useState<RecoveredRecording[]>([]);
+  // transcripts of recovered recordings, by path
+  const [recoveredText, setRecoveredText] = useState<Record<string, string>>({});
+  const [transcribingPath, setTranscribingPath] = useState<string | null>(null);
   const [loadingDevices, setLoadingDevices] = useState(true);
+  // audio file dropped on the window for transcription
+  const [importing, setImporting] = useState<string | null>(null);
+  const [importedText, setImportedText] = useState<string | null>(null);
+  const [dragOver, setDragOver] = useState(false);
   const googleLanguageOptions = [
     { label: "English (United States) — en-US", value: "en-US" },
     { label: "English (United Kingdom) — en-GB", value: "en-GB" },
@@ -48,6 +180,15 @@ export default function Settings() {
     // Load settings on mount
     loadSettings();
     loadInputDevices();
+    invoke<RecoveredRecording[]>("list_recovered_recordings")
+      .then(setRecovered)
+      .catch((e) => console.error("Failed to load recovered recordings:", e));
+    invoke<AudioHost[]>("list_audio_hosts")
+      .then(setAudioHosts)
+      .catch((e) => console.error("Failed to load audio hosts:", e));
+    invoke<string[]>("list_upload_formats")
+      .then(setUploadFormats)
+      .catch((e) => console.error("Failed to load upload formats:", e));
 
     // Listen for open-settings event
     const unlisten = listen("open-settings", () => {
@@ -55,15 +196,28 @@ export default function Settings() {
       loadInputDevices();
     });
 
+    const unlistenDrop = getCurrentWebview().onDragDropEvent((event) => {
+      if (event.payload.type === "over") {
+        setDragOver(true);
+      } else if (event.payload.type === "drop") {
+        setDragOver(false);
+        if (event.payload.paths.length > 0) handleTranscribeFile(event.payload.paths[0]);
+      } else {
+        setDragOver(false);
+      }
+    });
+
     return () => {
       unlisten.then((fn) => fn());
+      unlistenDrop.then((fn) => fn());
     };
   }, []);
 
-  const loadInputDevices = async () => {
+  // host undefined = the host saved in settings
+  const loadInputDevices = async (host?: string) => {
     try {
       setLoadingDevices(true);
-      const devices = await invoke<InputDevice[]>("list_input_devices");
+      const devices = await invoke<InputDevice[]>("list_input_devices", { host });
       setInputDevices(devices);
     } catch (e) {
       console.error("Failed to load input devices:", e);
@@ -77,11 +231,36 @@ export default function Settings() {
       setLoading(true);
       const loaded = await invoke<Settings>("get_settings");
       setSettings({
+        // keep fields this page doesn't edit so saving doesn't reset them
+        ...loaded,
         provider: loaded.provider || "openai",
         openai_api_key: loaded.openai_api_key || "",
         google_api_key: loaded.google_api_key || "",
         google_language: loaded.google_language || "en-US",
-        input_device_name: loaded.input_device_name || "",
+        audio_host: loaded.audio_host || "",
+        input_devices: loaded.input_devices ?? [],
+        input_configs: loaded.input_configs ?? {},
+        input_channel: loaded.input_channel ?? null,
+        target_sample_rate: loaded.target_sample_rate ?? 16000,
+        upload_format: loaded.upload_format || "wav",
+        system_audio: loaded.system_audio || "off",
+        system_audio_device: loaded.system_audio_device ?? "",
+        streaming_transcription: loaded.streaming_transcription ?? false,
+        segment_pause_ms: loaded.segment_pause_ms ?? 700,
+        segment_max_ms: loaded.segment_max_ms ?? 30000,
+        vad_auto_stop: loaded.vad_auto_stop ?? false,
+        vad_silence_ms: loaded.vad_silence_ms ?? 1500,
+        warm_mic: loaded.warm_mic ?? false,
+        preroll_ms: loaded.preroll_ms ?? 500,
+        min_recording_ms: loaded.min_recording_ms ?? 300,
+        min_speech_db: loaded.min_speech_db ?? -50,
+        hotkey_mode: loaded.hotkey_mode || "toggle",
+        push_to_talk_min_hold_ms: loaded.push_to_talk_min_hold_ms ?? 300,
+        cancel_hotkey: loaded.cancel_hotkey ?? "Escape",
+        pause_hotkey: loaded.pause_hotkey ?? "Ctrl+Shift+P",
+        delete_after_transcription: loaded.delete_after_transcription ?? false,
+        keep_recordings: loaded.keep_recordings ?? 0,
+        keep_recordings_days: loaded.keep_recordings_days ?? 0,
         panel_visible: loaded.panel_visible !== undefined ? loaded.panel_visible : true,
       });
     } catch (e) {
@@ -91,6 +270,129 @@ export default function Settings() {
     }
   };
 
+  const selectedDevice = inputDevices.find((device) =>
+    formatDeviceName ? device.name === formatDeviceName : device.is_default
+  );
+  // must match devices::config_key in the backend
+  const deviceConfigKey = (device: InputDevice) => `${device.host}/${device.name}`;
+  const selectedConfig = selectedDevice ? settings.input_configs[deviceConfigKey(selectedDevice)] : undefined;
+
+  // Rough preview of the backend's matching (it also matches by words)
+  const isConnected = (preference: string) =>
+    inputDevices.some((device) => device.name.toLowerCase().includes(preference.trim().toLowerCase()));
+
+  const setPreferences = (input_devices: string[]) => setSettings({ ...settings, input_devices });
+
+  const movePreference = (index: number, delta: number) => {
+    const list = [...settings.input_devices];
+    const [item] = list.splice(index, 1);
+    list.splice(index + delta, 0, item);
+    setPreferences(list);
+  };
+
+  const addPreference = (preference: string) => {
+    const trimmed = preference.trim();
+    if (trimmed && !settings.input_devices.includes(trimmed)) {
+      setPreferences([...settings.input_devices, trimmed]);
+    }
+    setNewPreference("");
+  };
+
+  const handleSelectConfig = async (key: string) => {
+    if (!selectedDevice) return;
+    const config = key
+      ? expandConfigs(selectedDevice.supported_configs).find((c) => configKey(c) === key) ?? null
+      : null;
+    try {
+      await invoke("select_input_config", {
+        host: settings.audio_host,
+        deviceName: selectedDevice.name,
+        config,
+      });
+      const input_configs = { ...settings.input_configs };
+      if (config) {
+        input_configs[deviceConfigKey(selectedDevice)] = config;
+      } else {
+        delete input_configs[deviceConfigKey(selectedDevice)];
+      }
+      setSettings({ ...settings, input_configs });
+    } catch (e) {
+      console.error("Failed to select input config:", e);
+      alert(`Failed to select input format: ${e}`);
+    }
+  };
+
+  const handleTestDevice = async () => {
+    try {
+      setTestingDevice(true);
+      setDeviceTest(null);
+      const result = await invoke<DeviceTest>("test_input_device", {
+        deviceName: formatDeviceName,
+        durationMs: 3000,
+      });
+      setDeviceTest(result);
+    } catch (e) {
+      console.error("Microphone test failed:", e);
+      alert(`Microphone test failed: ${e}`);
+    } finally {
+      setTestingDevice(false);
+    }
+  };
+
+  const playDeviceTest = () => {
+    if (deviceTest) {
+      new Audio(`data:audio/wav;base64,${deviceTest.wav_base64}`).play();
+    }
+  };
+
+  const handleTranscribeRecovered = async (path: string) => {
+    try {
+      setTranscribingPath(path);
+      const { text } = await invoke<{ text: string }>("transcribe_recovered_recording", { path });
+      setRecoveredText({ ...recoveredText, [path]: text });
+    } catch (e) {
+      console.error("Failed to transcribe recovered recording:", e);
+      alert(`Failed to transcribe: ${errorText(e)}`);
+    } finally {
+      setTranscribingPath(null);
+    }
+  };
+
+  const handleTranscribeFile = async (path: string) => {
+    try {
+      setImporting(path);
+      setImportedText(null);
+      const { text } = await invoke<{ text: string }>("transcribe_file", { path });
+      setImportedText(text);
+    } catch (e) {
+      console.error("Failed to transcribe file:", e);
+      alert(`Failed to transcribe: ${errorText(e)}`);
+    } finally {
+      setImporting(null);
+    }
+  };
+
+  const handleDiscardRecovered = async (path: string) => {
+    try {
+      await invoke("discard_recovered_recording", { path });
+      setRecovered(recovered.filter((r) => r.path !== path));
+    } catch (e) {
+      console.error("Failed to discard recovered recording:", e);
+      alert(`Failed to discard: ${errorText(e)}`);
+    }
+  };
+
+  const handlePurgeRecordings = async () => {
+    if (!confirm("Delete all saved recordings? This cannot be undone.")) return;
+    try {
+      const deleted = await invoke<number>("purge_recordings");
+      alert(`Deleted ${deleted} recording${deleted === 1 ? "" : "s"}.`);
+    } catch (e) {
+      console.error("Failed to purge recordings:", e);
+      alert(`Failed to delete recordings: ${e}`);
+    }
+  };
+
   const handleSave = async () => {
     try {
       await invoke("save_settings", { settings });
@@ -239,16 +541,197 @@ export default function Settings() {
         </div>
       </div>
 
+      {audioHosts.length > 1 && (
+        <div style={{ marginTop: 24 }}>
+          <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+            Audio System
+          </label>
+          <select
+            value={settings.audio_host}
+            onChange={(e) => {
+              // device names differ between hosts, so start from that host's default
+              setSettings({ ...settings, audio_host: e.target.value });
+              setFormatDeviceName("");
+              loadInputDevices(e.target.value);
+            }}
+            style={{
+              width: "100%",
+              padding: "8px 12px",
+              fontSize: 14,
+              border: "1px solid #ddd",
+              borderRadius: 4,
+              boxSizing: "border-box",
+            }}
+          >
+            <option value="">System default</option>
+            {audioHosts.map((host) => (
+              <option key={host.id} value={host.id}>
+                {host.id} {host.is_default ? "(Default)" : ""}
+              </option>
+            ))}
+          </select>
+          <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+            Which audio backend to record through. JACK also reaches PipeWire devices via pipewire-jack.
+          </div>
+        </div>
+      )}
+
       <div style={{ marginTop: 24 }}>
         <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
-          Audio Input Device
+          Preferred Microphones
         </label>
+        {settings.input_devices.length === 0 && (
+          <div style={{ fontSize: 14, color: "#666" }}>None, the system default is used.</div>
+        )}
+        {settings.input_devices.map((preference, index) => (
+          <div
+            key={preference}
+            style={{ display: "flex", alignItems: "center", gap: 8, fontSize: 14, marginBottom: 4 }}
+          >
+            <span style={{ width: 20, color: "#666" }}>{index + 1}.</span>
+            <span style={{ flex: 1 }}>{preference}</span>
+            <span style={{ fontSize: 12, color: isConnected(preference) ? "#28a745" : "#999" }}>
+              {isConnected(preference) ? "connected" : "not connected"}
+            </span>
+            <button disabled={index === 0} onClick={() => movePreference(index, -1)}>
+              ↑
+            </button>
+            <button
+              disabled={index === settings.input_devices.length - 1}
+              onClick={() => movePreference(index, 1)}
+            >
+              ↓
+            </button>
+            <button onClick={() => setPreferences(settings.input_devices.filter((_, i) => i !== index))}>
+              ✕
+            </button>
+          </div>
+        ))}
         {loadingDevices ? (
           <div style={{ fontSize: 14, color: "#666" }}>Loading devices...</div>
         ) : (
+          <div style={{ display: "flex", gap: 8, marginTop: 8 }}>
+            <input
+              list="input-device-names"
+              value={newPreference}
+              placeholder="Device name or part of it, e.g. Jabra"
+              onChange={(e) => setNewPreference(e.target.value)}
+              onKeyDown={(e) => {
+                if (e.key === "Enter") addPreference(newPreference);
+              }}
+              style={{
+                flex: 1,
+                padding: "8px 12px",
+                fontSize: 14,
+                border: "1px solid #ddd",
+                borderRadius: 4,
+                boxSizing: "border-box",
+              }}
+            />
+            <datalist id="input-device-names">
+              {inputDevices.filter((device) => !device.is_loopback).map((device) => (
+                <option key={device.name} value={device.name} />
+              ))}
+            </datalist>
+            <button onClick={() => addPreference(newPreference)}>Add</button>
+          </div>
+        )}
+        <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+          Each recording uses the first entry that matches a connected microphone, so a docked laptop can prefer the
+          headset and fall back to the built-in mic. Entries match the full name, part of it, or its words, so
+          "Jabra Evolve2" still finds "Headset (2- Jabra Evolve2 65)".
+        </div>
+        {!loadingDevices && inputDevices.length > 0 && (
+          <div style={{ marginTop: 12 }}>
+            <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+              Capture Format
+            </label>
+            <div style={{ display: "flex", gap: 8 }}>
+              <select
+                value={formatDeviceName}
+                onChange={(e) => setFormatDeviceName(e.target.value)}
+                style={{
+                  flex: 1,
+                  padding: "8px 12px",
+                  fontSize: 14,
+                  border: "1px solid #ddd",
+                  borderRadius: 4,
+                  boxSizing: "border-box",
+                }}
+              >
+                <option value="">Default (System Default)</option>
+                {inputDevices.map((device) => (
+                  <option key={device.name} value={device.name}>
+                    {device.name} {device.is_default ? "(Default)" : ""} {device.is_loopback ? "(System audio)" : ""}
+                  </option>
+                ))}
+              </select>
+              {selectedDevice && selectedDevice.supported_configs.length > 0 && (
+                <select
+                  value={selectedConfig ? configKey(selectedConfig) : ""}
+                  onChange={(e) => handleSelectConfig(e.target.value)}
+                  style={{
+                    flex: 1,
+                    padding: "8px 12px",
+                    fontSize: 14,
+                    border: "1px solid #ddd",
+                    borderRadius: 4,
+                    boxSizing: "border-box",
+                  }}
+                >
+                  <option value="">
+                    Device default
+                    {selectedDevice.default_config ? ` (${configLabel(selectedDevice.default_config)})` : ""}
+                  </option>
+                  {expandConfigs(selectedDevice.supported_configs).map((config) => (
+                    <option key={configKey(config)} value={configKey(config)}>
+                      {configLabel(config)}
+                    </option>
+                  ))}
+                </select>
+              )}
+            </div>
+            {selectedDevice && (
+              <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+                Formats reported by the {selectedDevice.host} backend. The recording is still resampled to the rate below.
+              </div>
+            )}
+            <div style={{ marginTop: 8, display: "flex", alignItems: "center", gap: 8 }}>
+              <button onClick={handleTestDevice} disabled={testingDevice}>
+                {testingDevice ? "Recording 3 s… speak now" : "Test microphone"}
+              </button>
+              {deviceTest && <button onClick={playDeviceTest}>▶ Play back</button>}
+            </div>
+            {deviceTest && (
+              <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+                {deviceTest.device}: peak {deviceTest.peak_db.toFixed(1)} dBFS, speech level{" "}
+                {deviceTest.loudest_frame_db.toFixed(1)} dBFS, {Math.round(deviceTest.silence_ratio * 100)}% silence
+                {deviceTest.clipped_samples > 0 ? (
+                  <span style={{ color: "#ff4444" }}>
+                    {" "}
+                    — clipping ({deviceTest.clipped_samples} samples), lower the input gain
+                  </span>
+                ) : deviceTest.loudest_frame_db < -40 ? (
+                  <span style={{ color: "#ff8800" }}> — very quiet, raise the input gain</span>
+                ) : (
+                  " — looks good"
+                )}
+              </div>
+            )}
+          </div>
+        )}
+        <div style={{ marginTop: 12 }}>
+          <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+            Input Channel
+          </label>
           <select
-            value={settings.input_device_name}
-            onChange={(e) => setSettings({ ...settings, input_device_name: e.target.value })}
+            value={settings.input_channel === null ? "" : String(settings.input_channel)}
+            onChange={(e) =>
+              setSettings({
+                ...settings,
+                input_channel: e.target.value === "" ? null : Number(e.target.value),
+              })
+            }
             style={{
               width: "100%",
               padding: "8px 12px",
@@ -258,16 +741,464 @@ export default function Settings() {
               boxSizing: "border-box",
             }}
           >
-            <option value="">Default (System Default)</option>
-            {inputDevices.map((device) => (
-              <option key={device.name} value={device.name}>
-                {device.name} {device.is_default ? "(Default)" : ""}
+            <option value="">Mix all channels</option>
+            {Array.from({ length: 8 }, (_, i) => (
+              <option key={i} value={String(i)}>
+                Channel {i + 1} only
               </option>
             ))}
           </select>
+          <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+            Multi-channel interfaces and array mics are mixed down to mono. Pick a single channel if your voice is only on one input.
+          </div>
+        </div>
+        <div style={{ marginTop: 12 }}>
+          <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+            Recording Sample Rate
+          </label>
+          <select
+            value={String(settings.target_sample_rate)}
+            onChange={(e) => setSettings({ ...settings, target_sample_rate: Number(e.target.value) })}
+            style={{
+              width: "100%",
+              padding: "8px 12px",
+              fontSize: 14,
+              border: "1px solid #ddd",
+              borderRadius: 4,
+              boxSizing: "border-box",
+            }}
+          >
+            <option value="16000">16 kHz (recommended)</option>
+            <option value="24000">24 kHz</option>
+            <option value="0">Device rate (no resampling)</option>
+          </select>
+          <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+            Audio is resampled before upload. 16 kHz is what OpenAI and Google use internally and keeps uploads small.
+          </div>
+        </div>
+        <div style={{ marginTop: 12 }}>
+          <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+            Upload Format
+          </label>
+          <select
+            value={settings.upload_format}
+            onChange={(e) => setSettings({ ...settings, upload_format: e.target.value })}
+            style={{
+              width: "100%",
+              padding: "8px 12px",
+              fontSize: 14,
+              border: "1px solid #ddd",
+              borderRadius: 4,
+              boxSizing: "border-box",
+            }}
+          >
+            {[
+              { value: "wav", label: "WAV (uncompressed)" },
+              { value: "flac", label: "FLAC (lossless, about half the size)" },
+              { value: "opus", label: "Ogg/Opus (smallest, slightly lossy)" },
+            ]
+              .filter((f) => uploadFormats.includes(f.value) || f.value === settings.upload_format)
+              .map((f) => (
+                <option key={f.value} value={f.value} disabled={!uploadFormats.includes(f.value)}>
+                  {uploadFormats.includes(f.value) ? f.label : `${f.label} — not available in this build`}
+                </option>
+              ))}
+          </select>
+          <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+            Recordings are compressed just before upload, which helps on slow connections. Opus is only offered in
+            builds with the opus feature.
+          </div>
+        </div>
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Warm Microphone
+        </label>
+        <label style={{ display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+          <input
+            type="checkbox"
+            checked={settings.warm_mic}
+            onChange={(e) => setSettings({ ...settings, warm_mic: e.target.checked })}
+          />
+          Keep the microphone open so the first word is never cut off
+        </label>
+        {settings.warm_mic && (
+          <div style={{ marginTop: 8, display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+            Include the last
+            <input
+              type="number"
+              min={100}
+              max={2000}
+              step={100}
+              value={settings.preroll_ms}
+              onChange={(e) => setSettings({ ...settings, preroll_ms: Number(e.target.value) })}
+              style={{ width: 80, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+            />
+            ms before the hotkey
+          </div>
+        )}
+        <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+          Privacy: while enabled the app listens continuously and keeps only the last few hundred milliseconds in memory.
+          Nothing is saved or sent until you start a recording. An orange dot on the floating panel shows the microphone is open.
+        </div>
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          System Audio
+        </label>
+        <select
+          value={settings.system_audio}
+          onChange={(e) => setSettings({ ...settings, system_audio: e.target.value })}
+          style={{
+            width: "100%",
+            padding: "8px 12px",
+            fontSize: 14,
+            border: "1px solid #ddd",
+            borderRadius: 4,
+            boxSizing: "border-box",
+          }}
+        >
+          <option value="off">Microphone only</option>
+          <option value="mix">Microphone and system audio, mixed</option>
+          <option value="separate">Microphone and system audio, separate channels</option>
+        </select>
+        {settings.system_audio !== "off" && (
+          <select
+            value={settings.system_audio_device}
+            onChange={(e) => setSettings({ ...settings, system_audio_device: e.target.value })}
+            style={{
+              width: "100%",
+              marginTop: 8,
+              padding: "8px 12px",
+              fontSize: 14,
+              border: "1px solid #ddd",
+              borderRadius: 4,
+              boxSizing: "border-box",
+            }}
+          >
+            <option value="">Detect automatically</option>
+            {settings.system_audio_device &&
+              !inputDevices.some((device) => device.name === settings.system_audio_device) && (
+                <option value={settings.system_audio_device}>{settings.system_audio_device} (not connected)</option>
+              )}
+            {/* loopback sources first; any input can be picked if the name check misses one */}
+            {[...inputDevices]
+              .sort((a, b) => Number(b.is_loopback) - Number(a.is_loopback))
+              .map((device) => (
+                <option key={device.name} value={device.name}>
+                  {device.name} {device.is_loopback ? "(System audio)" : ""}
+                </option>
+              ))}
+          </select>
+        )}
+        <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+          Records what is playing, such as a call or a video, alongside your voice. Separate channels keep your voice on
+          the left and system audio on the right. Linux uses the PulseAudio/PipeWire monitor source, Windows records the
+          speakers directly, and macOS needs a loopback driver such as BlackHole.
+          {settings.system_audio !== "off" &&
+            !loadingDevices &&
+            !inputDevices.some((device) => device.is_loopback) &&
+            " No system audio source was detected on this audio system."}
+        </div>
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Hands-free Mode
+        </label>
+        <label style={{ display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+          <input
+            type="checkbox"
+            checked={settings.vad_auto_stop}
+            onChange={(e) => setSettings({ ...settings, vad_auto_stop: e.target.checked })}
+          />
+          Stop recording automatically when I stop speaking
+        </label>
+        {settings.vad_auto_stop && (
+          <div style={{ marginTop: 8, display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+            Stop after
+            <input
+              type="number"
+              min={300}
+              max={10000}
+              step={100}
+              value={settings.vad_silence_ms}
+              onChange={(e) => setSettings({ ...settings, vad_silence_ms: Number(e.target.value) })}
+              style={{ width: 80, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+            />
+            ms of silence
+          </div>
+        )}
+        <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+          Transcription starts on its own once speech is followed by silence. The hotkey still stops recording at any time.
+        </div>
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Streaming Transcription
+        </label>
+        <label style={{ display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+          <input
+            type="checkbox"
+            checked={settings.streaming_transcription}
+            onChange={(e) => setSettings({ ...settings, streaming_transcription: e.target.checked })}
+          />
+          Transcribe long dictations in pieces while I'm still speaking
+        </label>
+        {settings.streaming_transcription && (
+          <div style={{ marginTop: 8, display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+            New piece after a
+            <input
+              type="number"
+              min={300}
+              max={5000}
+              step={100}
+              value={settings.segment_pause_ms}
+              onChange={(e) => setSettings({ ...settings, segment_pause_ms: Number(e.target.value) })}
+              style={{ width: 80, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+            />
+            ms pause, at most every
+            <input
+              type="number"
+              min={5}
+              max={55}
+              step={5}
+              value={settings.segment_max_ms / 1000}
+              onChange={(e) => setSettings({ ...settings, segment_max_ms: Number(e.target.value) * 1000 })}
+              style={{ width: 60, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+            />
+            s
+          </div>
+        )}
+        <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+          Each piece is sent as soon as you pause, so after you stop only the last few seconds are left to transcribe.
+          Uses the provider and key saved above.
+        </div>
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Hotkey Mode
+        </label>
+        <select
+          value={settings.hotkey_mode}
+          onChange={(e) => setSettings({ ...settings, hotkey_mode: e.target.value })}
+          style={{
+            width: "100%",
+            padding: "8px 12px",
+            fontSize: 14,
+            border: "1px solid #ddd",
+            borderRadius: 4,
+            boxSizing: "border-box",
+          }}
+        >
+          <option value="toggle">Toggle: press to start, press again to stop</option>
+          <option value="push_to_talk">Push-to-talk: hold to record, release to transcribe</option>
+        </select>
+        {settings.hotkey_mode === "push_to_talk" && (
+          <div style={{ marginTop: 8, display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+            Ignore taps shorter than
+            <input
+              type="number"
+              min={0}
+              max={2000}
+              step={50}
+              value={settings.push_to_talk_min_hold_ms}
+              onChange={(e) => setSettings({ ...settings, push_to_talk_min_hold_ms: Number(e.target.value) })}
+              style={{ width: 80, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+            />
+            ms
+          </div>
+        )}
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Cancel Hotkey
+        </label>
+        <input
+          type="text"
+          value={settings.cancel_hotkey}
+          placeholder="Escape"
+          onChange={(e) => setSettings({ ...settings, cancel_hotkey: e.target.value })}
+          style={{
+            width: "100%",
+            padding: "8px 12px",
+            fontSize: 14,
+            border: "1px solid #ddd",
+            borderRadius: 4,
+            boxSizing: "border-box",
+          }}
+        />
+        <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+          Discards the current recording without transcribing. Only active while recording, e.g. "Escape" or
+          "Ctrl+Shift+X". Leave empty to disable.
+        </div>
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Pause Hotkey
+        </label>
+        <input
+          type="text"
+          value={settings.pause_hotkey}
+          placeholder="Ctrl+Shift+P"
+          onChange={(e) => setSettings({ ...settings, pause_hotkey: e.target.value })}
+          style={{
+            width: "100%",
+            padding: "8px 12px",
+            fontSize: 14,
+            border: "1px solid #ddd",
+            borderRadius: 4,
+            boxSizing: "border-box",
+          }}
+        />
+        <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+          Pauses and resumes the current recording. Only active while recording. Leave empty to disable.
+        </div>
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Silence Filter
+        </label>
+        <div style={{ display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+          Skip recordings shorter than
+          <input
+            type="number"
+            min={0}
+            max={5000}
+            step={100}
+            value={settings.min_recording_ms}
+            onChange={(e) => setSettings({ ...settings, min_recording_ms: Number(e.target.value) })}
+            style={{ width: 80, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+          />
+          ms or quieter than
+          <input
+            type="number"
+            min={-90}
+            max={0}
+            step={5}
+            value={settings.min_speech_db}
+            onChange={(e) => setSettings({ ...settings, min_speech_db: Number(e.target.value) })}
+            style={{ width: 64, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+          />
+          dBFS
+        </div>
+        <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
+          These recordings are not sent for transcription, so silence never turns into made-up text.
+        </div>
+      </div>
+
+      {recovered.length > 0 && (
+        <div style={{ marginTop: 24, padding: 12, border: "1px solid #ffaa00", borderRadius: 4 }}>
+          <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+            Recovered Recordings
+          </label>
+          <div style={{ marginBottom: 8, fontSize: 12, color: "#666" }}>
+            These recordings were interrupted when the app quit unexpectedly.
+          </div>
+          {recovered.map((recording) => (
+            <div key={recording.path} style={{ marginBottom: 8, fontSize: 14 }}>
+              <div style={{ display: "flex", alignItems: "center", gap: 8 }}>
+                <span style={{ flex: 1 }}>{(recording.duration_ms / 1000).toFixed(1)} s recording</span>
+                {recoveredText[recording.path] === undefined ? (
+                  <button
+                    onClick={() => handleTranscribeRecovered(recording.path)}
+                    disabled={transcribingPath !== null}
+                  >
+                    {transcribingPath === recording.path ? "Transcribing..." : "Transcribe"}
+                  </button>
+                ) : (
+                  <button onClick={() => navigator.clipboard.writeText(recoveredText[recording.path])}>Copy</button>
+                )}
+                <button onClick={() => handleDiscardRecovered(recording.path)}>Discard</button>
+              </div>
+              {recoveredText[recording.path] !== undefined && (
+                <textarea
+                  readOnly
+                  value={recoveredText[recording.path]}
+                  style={{ width: "100%", marginTop: 4, fontSize: 13, boxSizing: "border-box" }}
+                />
+              )}
+            </div>
+          ))}
+        </div>
+      )}
+
+      <div
+        style={{
+          marginTop: 24,
+          padding: 12,
+          border: `1px dashed ${dragOver ? "#0066cc" : "#ccc"}`,
+          borderRadius: 4,
+        }}
+      >
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Transcribe a File
+        </label>
+        <div style={{ fontSize: 12, color: "#666" }}>
+          {importing
+            ? `Transcribing ${importing.split(/[\\/]/).pop()}...`
+            : `Drop an audio file (WAV, FLAC, MP3, Ogg Vorbis${
+                uploadFormats.includes("opus") ? ", Ogg/Opus" : ""
+              }, M4A) on this window to transcribe it with the selected provider.`}
+        </div>
+        {importedText !== null && (
+          <div style={{ marginTop: 8 }}>
+            <textarea
+              readOnly
+              value={importedText}
+              style={{ width: "100%", fontSize: 13, boxSizing: "border-box" }}
+            />
+            <button onClick={() => navigator.clipboard.writeText(importedText)}>Copy</button>
+          </div>
         )}
+      </div>
+
+      <div style={{ marginTop: 24 }}>
+        <label style={{ display: "block", marginBottom: 8, fontSize: 14, fontWeight: 500 }}>
+          Recording History
+        </label>
+        <label style={{ display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+          <input
+            type="checkbox"
+            checked={settings.delete_after_transcription}
+            onChange={(e) => setSettings({ ...settings, delete_after_transcription: e.target.checked })}
+          />
+          Delete each recording once it has been transcribed
+        </label>
+        <div style={{ marginTop: 8, display: "flex", alignItems: "center", gap: 8, fontSize: 14 }}>
+          Keep the last
+          <input
+            type="number"
+            min={0}
+            max={1000}
+            value={settings.keep_recordings}
+            onChange={(e) => setSettings({ ...settings, keep_recordings: Number(e.target.value) })}
+            style={{ width: 64, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+          />
+          recordings, for at most
+          <input
+            type="number"
+            min={0}
+            max={365}
+            value={settings.keep_recordings_days}
+            onChange={(e) => setSettings({ ...settings, keep_recordings_days: Number(e.target.value) })}
+            style={{ width: 64, padding: "4px 8px", fontSize: 14, border: "1px solid #ddd", borderRadius: 4 }}
+          />
+          days
+        </div>
+        <div style={{ marginTop: 8 }}>
+          <button onClick={handlePurgeRecordings}>Delete all recordings now</button>
+        </div>
         <div style={{ marginTop: 4, fontSize: 12, color: "#666" }}>
-          Select which microphone to use for recording. Leave as "Default" to use the system default.
+          Recordings are kept in the app cache folder. Once a limit is set, older ones are removed at startup and
+          before each new recording. 0 means no limit, and nothing is deleted unless you set one.
         </div>
       </div>
 